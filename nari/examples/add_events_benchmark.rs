@@ -0,0 +1,32 @@
+use std::time::Instant;
+
+use nari::models::{Database, EventId};
+
+const EVENTS: u64 = 1000;
+
+// Compares adding events one by one, which rewrites the event queue for each of
+// them, against adding all of them at once with a single rewrite
+fn main() {
+    let dir = std::env::temp_dir().join("nari_add_events_benchmark");
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let db = Database::new(dir.join("one_by_one")).unwrap();
+    let started = Instant::now();
+    for i in 0..EVENTS {
+        db.add_event(db.build_event(EventId(i), "One", 123456789 + i).build())
+            .unwrap();
+    }
+    let one_by_one = started.elapsed();
+
+    let db = Database::new(dir.join("at_once")).unwrap();
+    let events: Vec<_> = (0..EVENTS)
+        .map(|i| db.build_event(EventId(i), "All", 123456789 + i).build())
+        .collect();
+    let started = Instant::now();
+    db.add_events(events).unwrap();
+    let at_once = started.elapsed();
+
+    println!("add_event:  {one_by_one:?} for {EVENTS} events");
+    println!("add_events: {at_once:?} for {EVENTS} events");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,38 @@
+use std::time::Instant;
+
+use nari::models::{Database, EventId, User, UserId};
+
+const EVENTS: u64 = 200;
+const USERS_PER_EVENT: u64 = 10;
+
+// Compares adding events whose users are read back from the database against
+// attaching them to users that are already at hand
+fn main() {
+    let dir = std::env::temp_dir().join("nari_attach_benchmark");
+    let _ = std::fs::remove_dir_all(&dir);
+    let db = Database::new(&dir).unwrap();
+    let mut users: Vec<User> = (0..USERS_PER_EVENT)
+        .map(|i| db.create_user(UserId(i), &format!("User {i}")).unwrap())
+        .collect();
+
+    let started = Instant::now();
+    for i in 0..EVENTS {
+        db.build_event(EventId(i), "Fetched", 123456789)
+            .users(users.iter().map(|u| u.id))
+            .build()
+            .save_to_db(&db)
+            .unwrap();
+    }
+    let fetched = started.elapsed();
+
+    let started = Instant::now();
+    for i in EVENTS..2 * EVENTS {
+        let mut event = db.build_event(EventId(i), "Attached", 123456789).build();
+        db.attach_event(&mut event, &mut users).unwrap();
+    }
+    let attached = started.elapsed();
+
+    println!("add_event:    {fetched:?} for {EVENTS} events of {USERS_PER_EVENT} users");
+    println!("attach_event: {attached:?} for {EVENTS} events of {USERS_PER_EVENT} users");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
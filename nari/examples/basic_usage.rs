@@ -12,9 +12,9 @@ async fn main() {
     let db = Database::new("db/").unwrap();
     // We can initialize users manually
     let alice = User::new(UserId(1), "Alice");
-    db.add_user(alice.clone());
+    db.add_user(alice.clone()).unwrap();
     // Or create them from the database
-    let bob = db.create_user(UserId(2), "Bob");
+    let bob = db.create_user(UserId(2), "Bob").unwrap();
 
     // Same with events, using the builders is recommended
     // We provide an u64 unix timestamp
@@ -27,16 +27,17 @@ async fn main() {
     db.build_event(EventId(1), "Alice's Birthday", in_two_seconds)
         .users([alice.id])
         .build()
-        .save_to_db(&db);
+        .save_to_db(&db)
+        .unwrap();
 
     let ev = EventBuilder::new(EventId(2), "Monthly club meeting", in_two_seconds + 2)
         .description("Montly updates of the activites related to the club")
         .build();
-    db.add_event_to_users(ev, [alice, bob]);
+    db.add_event_to_users(ev, [alice, bob]).unwrap();
 
     // We use channels to listen to any incomming event
     let (event_transmiter, mut event_listener) = mpsc::channel(16);
-    let listener = EventListener::new(event_transmiter, 500);
+    let listener = EventListener::new(event_transmiter, 500, "db/");
     tokio::spawn(listener.start());
 
     // Since an `Event` can be represented in multiple equally valid and meaningful ways, it does not implement a Display
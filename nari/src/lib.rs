@@ -2,7 +2,8 @@
 //!
 //! It is built with consistency between runs in mind, to achieve this it saves most
 //! of its information in the filesystem, different approaches like using NoSQL/SQL
-//! databases or an entirely in-memory approach may come in the future.
+//! databases may come in the future. For tests, an entirely in-memory [`MemoryStore`]
+//! can be used in place of [`Database`] by code that is generic over [`Store`].
 //!   
 //! [`Event`] represents any possible event that can happen. It provides any possible
 //! important information that a event can have, check its documentation for further
@@ -17,6 +18,33 @@
 //! future events. It sends events through a mspc channel whenever their unix timestamp is
 //! reached, how often this condition is checked  
 //!
+//! ## Features
+//!
+//! - `display`: implements [`Display`](std::fmt::Display) for [`Event`] and provides
+//!   an [`EventFormatter`] to tweak the output, without it events have no `Display`.
+//! - `json`: allows exporting and importing the whole database as JSON, and saving its
+//!   files as JSON, see `Database::with_format`.
+//! - `ical`: allows exporting every event as an iCalendar (`.ics`) file.
+//! - `chrono`: allows scheduling events in a time zone, so that repeating events keep
+//!   their wall-clock time across daylight saving time changes.
+//! - `async`: adds non-blocking variants of the most common [`Database`] operations, like
+//!   `add_event_async`, for use inside of a tokio runtime.
+//! - `compress`: allows gzip compressing the files of a [`Database`], see
+//!   `Database::with_compression`.
+//! - `bincode`: allows saving the files of a [`Database`] as bincode, see
+//!   `Database::with_format`.
+//! - `stream`: allows receiving the events of a listener as a `futures` stream, see
+//!   `EventListenerBuilder::build_stream`.
+//! - `encryption`: allows encrypting the files of a [`Database`] at rest, see
+//!   `Database::with_key`.
+//! - `cache`: allows keeping the events fetched last in memory, see
+//!   `Database::with_event_cache`.
+//! - `natural`: allows reading when an event happens from text like `in 2 hours` or
+//!   `tomorrow 9am`, see `Database::build_event_at`.
+//! - `tracing`: records a [`tracing`](https://docs.rs/tracing) span for every operation
+//!   of a [`Database`], with the ids involved and the error if it fails. The contents of
+//!   users and events are never recorded.
+//!
 //! To see it in action you can look at [`examples`] to get a quick grasp on how to get running with nari.
 //!
 //! If you would rather have a fully fledged application ready, you can check our [`github repo`]
@@ -24,7 +52,10 @@
 //!
 //! [`Event`]: crate::models::event::Event
 //! [`Database`]: crate::models::Database
+//! [`MemoryStore`]: crate::models::MemoryStore
+//! [`Store`]: crate::models::Store
 //! [`EventListener`]: crate::models::event::EventListener
+//! [`EventFormatter`]: https://docs.rs/nari/latest/nari/models/event/struct.EventFormatter.html
 //! [`.ron`]: https://github.com/ron-rs/ron
 //! [`github repo`]: https://github.com/HiccupEnthusiast/Nari
 //! [`examples`]: https://github.com/HiccupEnthusiast/Nari/nari/examples
@@ -0,0 +1,332 @@
+use std::{collections::BTreeMap, io, path::Path};
+
+use ron::ser::PrettyConfig;
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::{fs, io::AsyncWriteExt, task};
+
+use super::{
+    codec::{decode, encode_pretty, Codec, SerdeFormat},
+    database::{consolidated_path, file_in, tmp_path, update_cache_at, update_consolidated},
+    event::Event,
+    queue::EventQueue,
+    schema::{read_versioned_all, upgrade, Versioned},
+    Database, EventId, Layout, NariError, User, UserId,
+};
+
+/// Variants of the most common operations that don't block the runtime, only
+/// available with the `async` feature.
+///
+/// Files are read and written with [`tokio::fs`], while the event queue, which has to
+/// be locked against other processes, is updated on tokio's blocking thread pool. They
+/// can be freely mixed with the blocking methods.
+impl Database {
+    /// Adds a user like [`add_user`](Database::add_user) does, without blocking.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::{Database, User, UserId};
+    /// # #[tokio::main]
+    /// # async fn main() -> std::io::Result<()> {
+    /// # let db = Database::new("./db/")?;
+    /// db.add_user_async(User::new(UserId(42), "Alice")).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(id = %user.id), err))]
+    pub async fn add_user_async(&self, user: User) -> Result<(), NariError> {
+        self.write_entry_async(user.id.0, "users", user).await
+    }
+    /// Searches for a user like [`fetch_user`](Database::fetch_user) does, without
+    /// blocking.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::{Database, UserId};
+    /// # #[tokio::main]
+    /// # async fn main() -> std::io::Result<()> {
+    /// # let db = Database::new("./db/")?;
+    /// if let Some(alice) = db.fetch_user_async(UserId(42)).await? {
+    ///     println!("{} has {} events", alice.name, alice.events.len());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(id = %id), err))]
+    pub async fn fetch_user_async(&self, id: UserId) -> Result<Option<User>, NariError> {
+        self.read_entry_async(id.0, "users").await
+    }
+    /// Adds an event and adds it to its users like [`add_event`](Database::add_event)
+    /// does, without blocking. Fails without saving anything if a user would be in more
+    /// events than allowed, see
+    /// [`with_max_events_per_user`](Database::with_max_events_per_user).
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::{event::EventBuilder, Database, EventId, UserId};
+    /// # #[tokio::main]
+    /// # async fn main() -> std::io::Result<()> {
+    /// # let db = Database::new("./db/")?;
+    /// let birthday = EventBuilder::new(EventId(14), "Alice's Birthday", 123456789)
+    ///     .users([UserId(42)])
+    ///     .build();
+    /// db.add_event_async(birthday).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(id = %event.id), err))]
+    pub async fn add_event_async(&self, event: Event) -> Result<(), NariError> {
+        let mut users = vec![];
+        for u in event.users.iter() {
+            if let Some(user) = self.fetch_user_async(*u).await? {
+                let joining = usize::from(!user.events.contains(&event.id));
+                self.check_event_capacity(&user, joining)?;
+                users.push(user);
+            }
+        }
+        self.write_entry_async(event.id.0, "events", event.clone())
+            .await?;
+        for mut user in users {
+            user.events.insert(event.id);
+            self.add_user_async(user).await?;
+        }
+        self.queue_event_async(&event).await
+    }
+    /// Replaces a stored event like [`update_event`](Database::update_event) does,
+    /// without blocking.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::{Database, EventId};
+    /// # #[tokio::main]
+    /// # async fn main() -> std::io::Result<()> {
+    /// # let db = Database::new("./db/")?;
+    /// if let Some(mut birthday) = db.fetch_event_async(EventId(14)).await? {
+    ///     birthday.description = String::from("Bring cake");
+    ///     db.update_event_async(birthday).await?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(id = %event.id), err))]
+    pub async fn update_event_async(&self, event: Event) -> Result<(), NariError> {
+        self.write_entry_async(event.id.0, "events", event.clone())
+            .await?;
+        self.queue_event_async(&event).await
+    }
+    /// Searches for an event like [`fetch_event`](Database::fetch_event) does, without
+    /// blocking.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::{Database, EventId};
+    /// # #[tokio::main]
+    /// # async fn main() -> std::io::Result<()> {
+    /// # let db = Database::new("./db/")?;
+    /// match db.fetch_event_async(EventId(14)).await? {
+    ///     Some(event) => println!("{} at {}", event.name, event.next_occurence),
+    ///     None => println!("No such event"),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(id = %id), err))]
+    pub async fn fetch_event_async(&self, id: EventId) -> Result<Option<Event>, NariError> {
+        #[cfg(feature = "cache")]
+        if self.caches_events() {
+            let modified = fs::metadata(self.entry_file(id.0, "events"))
+                .await
+                .and_then(|m| m.modified());
+            if let Ok(modified) = modified {
+                if let Some(event) = self.cached_event(id.0, modified) {
+                    return Ok(Some(event));
+                }
+                let event = self.read_entry_async(id.0, "events").await?;
+                if let Some(event) = &event {
+                    self.cache_event(modified, event);
+                }
+                return Ok(event);
+            }
+        }
+        self.read_entry_async(id.0, "events").await
+    }
+    /// Removes an event and takes it out of its users like
+    /// [`delete_event`](Database::delete_event) does, without blocking.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::{Database, EventId};
+    /// # #[tokio::main]
+    /// # async fn main() -> std::io::Result<()> {
+    /// # let db = Database::new("./db/")?;
+    /// if !db.delete_event_async(EventId(14)).await? {
+    ///     println!("There was nothing to delete");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(id = %id), err))]
+    pub async fn delete_event_async(&self, id: EventId) -> Result<bool, NariError> {
+        let event = match self.fetch_event_async(id).await? {
+            Some(event) => event,
+            None => return Ok(false),
+        };
+        self.update_cache_async(move |tree| tree.remove_id(id.0))
+            .await?;
+        for u in event.users.iter() {
+            if let Some(mut user) = self.fetch_user_async(*u).await? {
+                user.events.remove(&id);
+                self.add_user_async(user).await?;
+            }
+        }
+        self.remove_entry_async::<Event>(id.0, "events").await?;
+        Ok(true)
+    }
+    // Consolidated files are locked against other processes, so they go through
+    // tokio's blocking thread pool like the event queue
+    async fn read_entry_async<T>(&self, id: u64, folder: &str) -> Result<Option<T>, NariError>
+    where
+        T: DeserializeOwned + Versioned + Send + 'static,
+    {
+        if self.layout() == Layout::Consolidated {
+            let base_path = self.base_path().to_path_buf();
+            let path = consolidated_path(&base_path, folder, self.format());
+            let (format, codec) = (self.format(), self.codec());
+            return blocking(move || {
+                Ok(read_versioned_all(&base_path, &path, format, codec)?.remove(&id))
+            })
+            .await;
+        }
+        let path = self.entry_path(id, folder);
+        read_entry(self.base_path(), &path, self.format(), self.codec()).await
+    }
+    async fn write_entry_async<T>(&self, id: u64, folder: &str, value: T) -> Result<(), NariError>
+    where
+        T: Serialize + DeserializeOwned + Send + 'static,
+    {
+        self.forget_cached(id, folder);
+        if self.layout() == Layout::Consolidated {
+            let base_path = self.base_path().to_path_buf();
+            let path = consolidated_path(&base_path, folder, self.format());
+            let (mode, format, codec) = (self.lock_mode(), self.format(), self.codec());
+            let pretty = self.pretty().cloned();
+            return blocking(move || {
+                update_consolidated(
+                    &base_path,
+                    &path,
+                    mode,
+                    format,
+                    codec,
+                    pretty.as_ref(),
+                    |entries| {
+                        entries.insert(id, value);
+                    },
+                )
+            })
+            .await;
+        }
+        write_atomically(
+            self.base_path(),
+            &self.entry_path(id, folder),
+            &value,
+            self.format(),
+            self.codec(),
+            self.pretty(),
+        )
+        .await
+    }
+    async fn remove_entry_async<T>(&self, id: u64, folder: &str) -> Result<(), NariError>
+    where
+        T: Serialize + DeserializeOwned + Send + 'static,
+    {
+        self.forget_cached(id, folder);
+        if self.layout() == Layout::Consolidated {
+            let base_path = self.base_path().to_path_buf();
+            let path = consolidated_path(&base_path, folder, self.format());
+            let (mode, format, codec) = (self.lock_mode(), self.format(), self.codec());
+            return blocking(move || {
+                update_consolidated(
+                    &base_path,
+                    &path,
+                    mode,
+                    format,
+                    codec,
+                    None,
+                    |entries: &mut BTreeMap<u64, T>| {
+                        entries.remove(&id);
+                    },
+                )
+            })
+            .await;
+        }
+        Ok(fs::remove_file(self.entry_path(id, folder)).await?)
+    }
+    // Queued once its file is written, as listeners drop the ids whose files they
+    // can't find
+    async fn queue_event_async(&self, event: &Event) -> Result<(), NariError> {
+        let (id, fires_at) = (event.id.0, event.queued_at());
+        self.update_cache_async(move |tree| {
+            tree.remove_id(id);
+            if let Some(at) = fires_at {
+                tree.insert(at, id);
+            }
+        })
+        .await
+    }
+    async fn update_cache_async<F>(&self, f: F) -> Result<(), NariError>
+    where
+        F: FnOnce(&mut EventQueue) + Send + 'static,
+    {
+        let (base_path, cache_path) = (self.base_path().to_path_buf(), self.cache_path());
+        let (mode, format, codec) = (self.lock_mode(), self.format(), self.codec());
+        blocking(move || update_cache_at(&base_path, &cache_path, mode, format, codec, f)).await
+    }
+}
+
+async fn blocking<T, F>(f: F) -> Result<T, NariError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, NariError> + Send + 'static,
+{
+    task::spawn_blocking(f)
+        .await
+        .map_err(|e| NariError::Io(io::Error::other(e)))?
+}
+
+async fn read_entry<T>(
+    base_path: &Path,
+    path: &Path,
+    format: SerdeFormat,
+    codec: Codec,
+) -> Result<Option<T>, NariError>
+where
+    T: DeserializeOwned + Versioned,
+{
+    let bytes = match fs::read(path).await {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let mut value =
+        decode(&bytes, format, codec, file_in(base_path, path)).map_err(|e| e.in_file(path))?;
+    upgrade(&mut value).map_err(|e| e.in_file(path))?;
+    Ok(Some(value))
+}
+/// Works like the blocking `write_atomically`.
+async fn write_atomically<T>(
+    base_path: &Path,
+    path: &Path,
+    value: &T,
+    format: SerdeFormat,
+    codec: Codec,
+    pretty: Option<&PrettyConfig>,
+) -> Result<(), NariError>
+where
+    T: Serialize,
+{
+    let bytes = encode_pretty(value, format, codec, pretty, file_in(base_path, path))?;
+    let tmp_path = tmp_path(path);
+    let mut file = fs::File::create(&tmp_path).await?;
+    file.write_all(&bytes).await?;
+    file.sync_all().await?;
+    Ok(fs::rename(tmp_path, path).await?)
+}
@@ -0,0 +1,47 @@
+pub(crate) const SECS_PER_HOUR: u64 = 60 * 60;
+pub(crate) const SECS_PER_DAY: u64 = 24 * SECS_PER_HOUR;
+
+// Moves `timestamp` ahead by `months`, keeping the time of the day, to `day` or the
+// last day of the target month if it is shorter
+pub(crate) fn add_months(timestamp: u64, months: u32, day: u32) -> Option<u64> {
+    let secs = timestamp % SECS_PER_DAY;
+    let (year, month, _) = civil_from_days((timestamp / SECS_PER_DAY) as i64);
+
+    let total = year * 12 + i64::from(month - 1) + i64::from(months);
+    let (year, month) = (total.div_euclid(12), total.rem_euclid(12) as u32 + 1);
+    let day = day.min(days_in_month(year, month));
+
+    let days = u64::try_from(days_from_civil(year, month, day)).ok()?;
+    days.checked_mul(SECS_PER_DAY)?.checked_add(secs)
+}
+pub(crate) fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+// Conversions between days since the unix epoch and (year, month, day) in the
+// proleptic gregorian calendar, see http://howardhinnant.github.io/date_algorithms.html
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = i64::from((month + 9) % 12);
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
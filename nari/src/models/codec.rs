@@ -0,0 +1,357 @@
+use std::{borrow::Cow, path::Path};
+
+use ron::ser::PrettyConfig;
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::NariError;
+
+/// First bytes of every gzip stream, RON files can't start with them.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+/// First bytes of every encrypted file, followed by the nonce and the ciphertext.
+const ENCRYPTED_MAGIC: [u8; 8] = *b"\0NARIENC";
+/// Length of the nonces of XChaCha20-Poly1305.
+#[cfg(feature = "encryption")]
+const NONCE_LEN: usize = 24;
+
+/// How the files of a [`Database`](super::Database) are encoded on disk, see
+/// `Database::with_compression`.
+///
+/// The encoding of each file is recognized when reading it, so a database can hold
+/// files encoded in different ways, except for encrypted databases, which only read
+/// the files encrypted with their key.
+#[non_exhaustive]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    /// Plain RON files.
+    #[default]
+    Plain,
+    /// Gzip compressed RON files, only available with the `compress` feature.
+    #[cfg(feature = "compress")]
+    Gzip,
+    /// RON files encrypted with XChaCha20-Poly1305 under the given key, only available
+    /// with the `encryption` feature, see `Database::with_key`.
+    #[cfg(feature = "encryption")]
+    Encrypted(EncryptionKey),
+}
+impl Codec {
+    /// Returns how `bytes` are encoded.
+    pub(super) fn detect(bytes: &[u8]) -> Self {
+        match bytes {
+            #[cfg(feature = "compress")]
+            [0x1f, 0x8b, ..] => Self::Gzip,
+            _ => Self::Plain,
+        }
+    }
+}
+
+/// The 256-bit key the files of an encrypted [`Database`](super::Database) are
+/// encrypted with, only available with the `encryption` feature.
+///
+/// It is never printed, not even with [`Debug`].
+///
+/// ## Usage
+/// ```
+/// # use nari::models::{Codec, EncryptionKey};
+/// let key = EncryptionKey::from([7; 32]);
+/// assert_eq!(format!("{key:?}"), "EncryptionKey(..)");
+/// assert_eq!(Codec::Encrypted(key), Codec::Encrypted([7; 32].into()));
+/// ```
+#[cfg(feature = "encryption")]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct EncryptionKey([u8; 32]);
+#[cfg(feature = "encryption")]
+impl EncryptionKey {
+    fn cipher(&self) -> chacha20poly1305::XChaCha20Poly1305 {
+        use chacha20poly1305::KeyInit;
+
+        chacha20poly1305::XChaCha20Poly1305::new(&self.0.into())
+    }
+    /// Encrypts `plaintext` under a new random nonce, prefixed by the magic bytes and
+    /// the nonce, bound to `file` so that it can only be decrypted from there.
+    fn encrypt(&self, plaintext: &[u8], file: &Path) -> Result<Vec<u8>, NariError> {
+        use chacha20poly1305::{
+            aead::{Aead, OsRng, Payload},
+            AeadCore, XChaCha20Poly1305,
+        };
+
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let aad = associated_data(file);
+        let ciphertext = self
+            .cipher()
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: plaintext,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| NariError::InvalidData(String::from("couldn't encrypt the file")))?;
+        let mut bytes = Vec::with_capacity(ENCRYPTED_MAGIC.len() + NONCE_LEN + ciphertext.len());
+        bytes.extend_from_slice(&ENCRYPTED_MAGIC);
+        bytes.extend_from_slice(&nonce);
+        bytes.extend_from_slice(&ciphertext);
+        Ok(bytes)
+    }
+    /// Decrypts `bytes` written by [`encrypt`](EncryptionKey::encrypt) for `file`.
+    fn decrypt(&self, bytes: &[u8], file: &Path) -> Result<Vec<u8>, NariError> {
+        use chacha20poly1305::{
+            aead::{Aead, Payload},
+            XNonce,
+        };
+
+        let rest = &bytes[ENCRYPTED_MAGIC.len()..];
+        if rest.len() < NONCE_LEN {
+            return Err(NariError::InvalidData(String::from(
+                "the encrypted file is truncated",
+            )));
+        }
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        let aad = associated_data(file);
+        self.cipher()
+            .decrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| {
+                NariError::InvalidData(String::from(
+                    "couldn't decrypt the file, the key is wrong, the file is corrupt or \
+                     was moved from elsewhere",
+                ))
+            })
+    }
+}
+/// What an encrypted file is bound to, its path inside of the database with `/`
+/// between the folders on every platform.
+#[cfg(feature = "encryption")]
+fn associated_data(file: &Path) -> Vec<u8> {
+    let parts: Vec<_> = file
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect();
+    parts.join("/").into_bytes()
+}
+#[cfg(feature = "encryption")]
+impl From<[u8; 32]> for EncryptionKey {
+    fn from(key: [u8; 32]) -> Self {
+        Self(key)
+    }
+}
+#[cfg(feature = "encryption")]
+impl std::fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EncryptionKey(..)")
+    }
+}
+
+/// The serialization format of the files of a [`Database`](super::Database), see
+/// `Database::with_format`.
+///
+/// Unlike the [`Codec`], the format isn't recognized when reading, every file of a
+/// database is expected to be in the same format. The files are named after it, like
+/// `14.json` for an event in JSON.
+///
+/// ## Usage
+/// ```
+/// # use nari::models::SerdeFormat;
+/// assert_eq!(SerdeFormat::default(), SerdeFormat::Ron);
+/// assert_eq!(SerdeFormat::Ron.extension(), "ron");
+/// ```
+#[non_exhaustive]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SerdeFormat {
+    /// Human readable [RON](https://github.com/ron-rs/ron) files.
+    #[default]
+    Ron,
+    /// JSON files, only available with the `json` feature.
+    #[cfg(feature = "json")]
+    Json,
+    /// [Bincode](https://docs.rs/bincode) files, smaller and faster to read and write
+    /// than the text formats but not readable by humans, only available with the
+    /// `bincode` feature.
+    #[cfg(feature = "bincode")]
+    Bincode,
+}
+impl SerdeFormat {
+    /// Returns the extension of the files in this format, without the dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Ron => "ron",
+            #[cfg(feature = "json")]
+            Self::Json => "json",
+            #[cfg(feature = "bincode")]
+            Self::Bincode => "bin",
+        }
+    }
+    fn is_text(self) -> bool {
+        #[cfg(feature = "bincode")]
+        if self == Self::Bincode {
+            return false;
+        }
+        true
+    }
+    fn serialize<T>(self, value: &T, pretty: Option<&PrettyConfig>) -> Result<Vec<u8>, NariError>
+    where
+        T: Serialize,
+    {
+        Ok(match self {
+            Self::Ron => match pretty {
+                Some(config) => ron::ser::to_string_pretty(value, config.clone())?.into_bytes(),
+                None => ron::ser::to_string(value)?.into_bytes(),
+            },
+            #[cfg(feature = "json")]
+            Self::Json if pretty.is_some() => serde_json::to_vec_pretty(value)?,
+            #[cfg(feature = "json")]
+            Self::Json => serde_json::to_vec(value)?,
+            #[cfg(feature = "bincode")]
+            Self::Bincode => bincode::serialize(value)?,
+        })
+    }
+    fn deserialize<T>(self, bytes: &[u8]) -> Result<T, NariError>
+    where
+        T: DeserializeOwned,
+    {
+        Ok(match self {
+            Self::Ron => ron::de::from_bytes(bytes)?,
+            #[cfg(feature = "json")]
+            Self::Json => serde_json::from_slice(bytes)?,
+            #[cfg(feature = "bincode")]
+            Self::Bincode => bincode::deserialize(bytes)?,
+        })
+    }
+}
+
+/// Serializes `value` in `format` encoded with `codec`, for the file at `file` inside
+/// of the database.
+pub(super) fn encode<T>(
+    value: &T,
+    format: SerdeFormat,
+    codec: Codec,
+    file: &Path,
+) -> Result<Vec<u8>, NariError>
+where
+    T: Serialize,
+{
+    encode_pretty(value, format, codec, None, file)
+}
+
+/// Serializes `value` like [`encode`], pretty printed with `pretty` if there is one
+/// and `format` is a text format.
+#[cfg_attr(not(feature = "encryption"), allow(unused_variables))]
+pub(super) fn encode_pretty<T>(
+    value: &T,
+    format: SerdeFormat,
+    codec: Codec,
+    pretty: Option<&PrettyConfig>,
+    file: &Path,
+) -> Result<Vec<u8>, NariError>
+where
+    T: Serialize,
+{
+    let serialized = format.serialize(value, pretty)?;
+    match codec {
+        Codec::Plain => Ok(serialized),
+        #[cfg(feature = "compress")]
+        Codec::Gzip => {
+            use std::io::Write;
+
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(&serialized)?;
+            Ok(encoder.finish()?)
+        }
+        #[cfg(feature = "encryption")]
+        Codec::Encrypted(key) => key.encrypt(&serialized, file),
+    }
+}
+
+/// Deserializes `format` from `bytes`, the file at `file` inside of the database, in
+/// any of the encodings of [`Codec`].
+///
+/// With the key of an encrypted `codec`, only files encrypted with it for `file` are
+/// read, see `Database::encrypt_files` for the ones written without it.
+pub(super) fn decode<T>(
+    bytes: &[u8],
+    format: SerdeFormat,
+    codec: Codec,
+    file: &Path,
+) -> Result<T, NariError>
+where
+    T: DeserializeOwned,
+{
+    format.deserialize(&serialized(bytes, format, codec, file)?)
+}
+
+/// Encrypts `bytes`, the file at `file` inside of the database, with `key` if it was
+/// written without one, returns `None` if it is already encrypted with it.
+#[cfg(feature = "encryption")]
+pub(super) fn encrypt_plain(
+    bytes: &[u8],
+    format: SerdeFormat,
+    key: EncryptionKey,
+    file: &Path,
+) -> Result<Option<Vec<u8>>, NariError> {
+    if bytes.starts_with(&ENCRYPTED_MAGIC) {
+        // Never encrypted twice, a file under another key is left for the user to see
+        key.decrypt(bytes, file)?;
+        return Ok(None);
+    }
+    let serialized = serialized(bytes, format, Codec::Plain, file)?;
+    key.encrypt(&serialized, file).map(Some)
+}
+
+/// Returns the serialized value held by `bytes`, undoing the encoding of the file.
+#[cfg_attr(not(feature = "encryption"), allow(unused_variables))]
+fn serialized<'a>(
+    bytes: &'a [u8],
+    format: SerdeFormat,
+    codec: Codec,
+    file: &Path,
+) -> Result<Cow<'a, [u8]>, NariError> {
+    #[cfg(feature = "encryption")]
+    if let Codec::Encrypted(key) = codec {
+        // Otherwise anyone able to write the files could slip in their own
+        if !bytes.starts_with(&ENCRYPTED_MAGIC) {
+            return Err(NariError::InvalidData(String::from(
+                "the file isn't encrypted, files written before the database had a key \
+                 have to be encrypted with `Database::encrypt_files`",
+            )));
+        }
+        return key.decrypt(bytes, file).map(Cow::Owned);
+    }
+    // Binary formats can start like an encrypted file by chance
+    if bytes.starts_with(&ENCRYPTED_MAGIC) && format.is_text() {
+        return Err(NariError::InvalidData(String::from(
+            if cfg!(feature = "encryption") {
+                "the file is encrypted, the database has to be opened with its key"
+            } else {
+                "the file is encrypted, which requires the `encryption` feature"
+            },
+        )));
+    }
+    match Codec::detect(bytes) {
+        // Binary formats can start like a gzip stream by chance, text ones can't
+        Codec::Plain if bytes.starts_with(&GZIP_MAGIC) && format.is_text() => {
+            Err(NariError::InvalidData(String::from(
+                "the file is compressed, which requires the `compress` feature",
+            )))
+        }
+        Codec::Plain => Ok(Cow::Borrowed(bytes)),
+        #[cfg(feature = "compress")]
+        Codec::Gzip => {
+            use std::io::Read;
+
+            let mut serialized = vec![];
+            match flate2::read::GzDecoder::new(bytes).read_to_end(&mut serialized) {
+                Ok(_) => Ok(Cow::Owned(serialized)),
+                Err(_) if !format.is_text() => Ok(Cow::Borrowed(bytes)),
+                Err(e) => Err(NariError::InvalidData(format!("corrupt gzip stream: {e}"))),
+            }
+        }
+        // Never detected, encrypted files are decrypted above
+        #[cfg(feature = "encryption")]
+        Codec::Encrypted(_) => Ok(Cow::Borrowed(bytes)),
+    }
+}
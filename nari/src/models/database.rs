@@ -1,22 +1,274 @@
 use file_lock::{FileLock, FileOptions};
+use ron::ser::PrettyConfig;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-use super::{event::Event, event::EventBuilder, EventId, User, UserId};
+#[cfg(feature = "encryption")]
+use super::codec::encrypt_plain;
+use super::codec::{decode, encode, encode_pretty, Codec, SerdeFormat};
+#[cfg(feature = "ical")]
+use super::ical;
+#[cfg(feature = "cache")]
+use super::lru::EventLru;
+#[cfg(feature = "natural")]
+use super::natural::{parse_time, ParseError};
+use super::queue::EventQueue;
+#[cfg(feature = "json")]
+use super::schema::upgrade;
+use super::schema::{read_versioned, read_versioned_all, Versioned};
+use super::{
+    event::{clamp_refresh_rate, Event, EventBuilder, Priority},
+    EventId, NariError, Timestamp, User, UserId,
+};
+#[cfg(feature = "ical")]
+use std::io::BufWriter;
+#[cfg(feature = "cache")]
+use std::time::SystemTime;
 use std::{
-    collections::BTreeMap,
-    fs::{create_dir_all, File},
-    io::{self, BufReader, BufWriter, Read},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fs::{self, create_dir_all, File},
+    io::{self, Read, Seek, SeekFrom, Write},
     path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Condvar, Mutex,
+    },
+    time::{Duration, Instant},
 };
 
+/// Files locked by a thread of this process, see [`lock_file`].
+static HELD_LOCKS: Mutex<BTreeSet<PathBuf>> = Mutex::new(BTreeSet::new());
+/// Notified whenever a file in [`HELD_LOCKS`] is released.
+static LOCK_RELEASED: Condvar = Condvar::new();
+static TMP_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Name of the file keeping track of the ids handed out, see [`Database::next_event_id`].
+const ID_COUNTERS_FILE: &str = "id_counters.ron";
+
+/// Last ids handed out by [`Database::next_event_id`] and [`Database::next_user_id`].
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct IdCounters {
+    events: u64,
+    users: u64,
+}
+
+#[cfg(feature = "json")]
+#[derive(Serialize, Deserialize)]
+struct JsonExport {
+    users: Vec<User>,
+    events: Vec<Event>,
+}
+
+/// Longest pause between attempts to take a lock with [`LockMode::Retry`].
+const MAX_RETRY_PAUSE: Duration = Duration::from_millis(50);
+
+/// How to wait for the locks that keep concurrent writers of a database in line.
+///
+/// Blocking waits for as long as it takes, so a process that never releases a lock
+/// stalls every other writer, and when used inside of an async runtime the worker
+/// thread along with them. Retrying gives up with [`NariError::LockTimeout`] instead,
+/// which keeps the program responsive at the cost of failing writes that would have
+/// eventually gone through.
+///
+/// Either way the waiting puts the thread to sleep, so [`Database`] methods called
+/// from async code should go through `spawn_blocking` like the
+/// [`EventListener`](super::event::EventListener) does. Writes never overlap, the
+/// locks are always held while writing.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LockMode {
+    #[default]
+    Blocking,
+    /// Tries to take the lock again and again, sleeping the thread for a growing
+    /// pause of up to 50 milliseconds between attempts, until `timeout` passes.
+    Retry { timeout: Duration },
+}
+
+/// How a database keeps its users and events on disk, see [`Database::with_layout`].
+#[non_exhaustive]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// One file per user in `users/` and one per event in `events/`.
+    #[default]
+    Files,
+    /// Every user in a single `users.ron` and every event in a single `events.ron`,
+    /// each holding a map from ids to entries.
+    Consolidated,
+}
+
+/// How a database is saved, to combine the options of the constructors of
+/// [`Database`] in [`Database::with_options`] and [`Database::open_with`].
+///
+/// Every [`Database`] opening the same files has to be given the same options.
+///
+/// ## Usage
+/// ```
+/// # use nari::models::{Database, DatabaseOptions, EventId, SerdeFormat};
+/// # fn main() -> std::io::Result<()> {
+/// # #[cfg(feature = "json")]
+/// # {
+/// let dir = std::env::temp_dir().join("nari_database_options");
+/// # let _ = std::fs::remove_dir_all(&dir);
+/// let options = DatabaseOptions::new().format(SerdeFormat::Json).shards(4);
+/// let db = Database::with_options(&dir, options.clone())?;
+/// db.build_event(EventId(1), "Tea time", 1000).build().save_to_db(&db)?;
+/// assert!(dir.join("events/1/1.json").is_file());
+///
+/// let db = Database::open_with(&dir, options)?;
+/// assert_eq!(db.fetch_event(EventId(1))?.unwrap().name, "Tea time");
+/// assert!(Database::open(&dir).is_err());
+/// # std::fs::remove_dir_all(&dir)?;
+/// # }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct DatabaseOptions {
+    format: SerdeFormat,
+    codec: Codec,
+    shards: u64,
+    layout: Layout,
+}
+impl DatabaseOptions {
+    /// Creates the options [`Database::new`] uses.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Saves users, events and the event queue in `format`, see
+    /// [`Database::with_format`].
+    pub fn format(mut self, format: SerdeFormat) -> Self {
+        self.format = format;
+        self
+    }
+    /// Encodes every file with `codec`, see [`Database::with_compression`]. Only
+    /// available with the `compress` feature.
+    #[cfg(feature = "compress")]
+    pub fn compression(mut self, codec: Codec) -> Self {
+        self.codec = codec;
+        self
+    }
+    /// Encrypts every file with `key`, see [`Database::with_key`]. Files are either
+    /// compressed or encrypted, so this replaces any
+    /// [`compression`](DatabaseOptions::compression). Only available with the
+    /// `encryption` feature.
+    #[cfg(feature = "encryption")]
+    pub fn key(mut self, key: [u8; 32]) -> Self {
+        self.codec = Codec::Encrypted(key.into());
+        self
+    }
+    /// Spreads the events over `shards` folders, see [`Database::with_shards`].
+    pub fn shards(mut self, shards: u64) -> Self {
+        self.shards = shards;
+        self
+    }
+    /// Keeps the users and events in the given [`Layout`], see
+    /// [`Database::with_layout`].
+    pub fn layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+}
+
+/// How [`Database::merge_from`] decides between two different events with the same id.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// The event already in the database is kept.
+    #[default]
+    KeepOurs,
+    /// The event from the other database replaces it.
+    KeepTheirs,
+    /// The event with the later next occurence is kept, as it has most likely been
+    /// rescheduled or fired more recently. Ties keep ours.
+    LatestOccurence,
+    /// The event with the higher [`Priority`] is kept. Ties keep ours.
+    HighestPriority,
+}
+
+/// What [`Database::merge_from`] did with the users and events of the other database.
+///
+/// Users and events identical in both databases count as skipped.
+#[non_exhaustive]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MergeReport {
+    pub users_added: usize,
+    pub users_overwritten: usize,
+    pub users_skipped: usize,
+    pub events_added: usize,
+    pub events_overwritten: usize,
+    pub events_skipped: usize,
+}
+
+/// Aggregate information about the events of a database, see [`Database::stats`].
+#[non_exhaustive]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct DatabaseStats {
+    /// How many events there are.
+    pub total: usize,
+    /// How many events there are of each priority, leaving out the ones without events.
+    pub by_priority: BTreeMap<Priority, usize>,
+    /// How many events there are of each repeatability, by its name like `weekly` or
+    /// `custom:3600`, leaving out the ones without events.
+    pub by_repeatability: BTreeMap<String, usize>,
+    /// The earliest next occurence of any event, `None` without events.
+    pub earliest: Option<Timestamp>,
+    /// The latest next occurence of any event, `None` without events.
+    pub latest: Option<Timestamp>,
+}
+
+/// Where the event queue and the event files disagree, see [`Database::verify`].
+#[non_exhaustive]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// Events queued at a different time than their file says.
+    pub mismatched: Vec<CacheMismatch>,
+    /// Events in the queue whose file doesn't exist.
+    pub missing_files: Vec<EventId>,
+    /// Events whose file says they are pending but aren't in the queue.
+    pub unqueued: Vec<EventId>,
+}
+impl VerifyReport {
+    /// Returns whether the event queue and the event files agree.
+    pub fn is_clean(&self) -> bool {
+        self.mismatched.is_empty() && self.missing_files.is_empty() && self.unqueued.is_empty()
+    }
+}
+
+/// An event queued at a different time than its file says, see [`VerifyReport`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheMismatch {
+    pub id: EventId,
+    /// When the event is queued to fire.
+    pub queued_at: u64,
+    /// When the event should be queued to fire, `None` if it shouldn't be queued at
+    /// all because it is paused.
+    pub expected: Option<u64>,
+}
+
 /// Main interface to interact with the internal files
 pub struct Database {
     base_path: PathBuf,
+    cache_name: String,
+    lock_mode: LockMode,
+    format: SerdeFormat,
+    codec: Codec,
+    shards: u64,
+    layout: Layout,
+    pretty: Option<PrettyConfig>,
+    max_events_per_user: Option<usize>,
+    #[cfg(feature = "cache")]
+    hot_events: Option<Mutex<EventLru>>,
 }
 impl Database {
     /// Creates a new database representation, if using a filesystem schema,
     /// it accepts the relative path where the file and folders will be created,
     /// does not create a new folder to contain the rest of the database.
     ///
+    /// An existing database at `base_path` is opened as it is, anything missing is
+    /// created, so a mistyped path silently creates an empty database. Use
+    /// [`open`](Database::open) to only open existing databases.
+    ///
+    /// The event queue is saved as `event_cache.ron`, use
+    /// [`with_cache_name`](Database::with_cache_name) to choose a different name.
+    /// A new queue starts out empty.
+    ///
     /// ### Usage
     /// ```no_run
     /// # use nari::models::Database;
@@ -26,152 +278,2452 @@ impl Database {
     /// # Ok(())
     /// # }
     /// ```
-    pub fn new<P>(base_path: P) -> io::Result<Self>
+    pub fn new<P>(base_path: P) -> Result<Self, NariError>
+    where
+        P: AsRef<Path>,
+    {
+        Self::with_options(base_path, DatabaseOptions::default())
+    }
+    /// Creates a new database representation just like [`new`](Database::new), saved
+    /// with all of `options` at once, see [`DatabaseOptions`].
+    pub fn with_options<P>(base_path: P, options: DatabaseOptions) -> Result<Self, NariError>
+    where
+        P: AsRef<Path>,
+    {
+        let cache_name = cache_file_name(options.format);
+        Self::create(base_path.as_ref(), &cache_name, &options)
+    }
+    /// Opens the existing database at `base_path`, without creating anything.
+    ///
+    /// Fails with [`NariError::NotFound`] if there is no folder at `base_path`, or if
+    /// the folder has no event queue, telling which of the two is missing. Use
+    /// [`new`](Database::new) to create the database if it doesn't exist.
+    ///
+    /// ### Usage
+    /// ```
+    /// # use nari::models::{Database, NariError};
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_open");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let err = Database::open(&dir).err().unwrap();
+    /// assert!(err.to_string().starts_with("database"));
+    ///
+    /// std::fs::create_dir_all(&dir)?;
+    /// let err = Database::open(&dir).err().unwrap();
+    /// assert!(err.to_string().starts_with("event queue"));
+    ///
+    /// Database::new(&dir)?;
+    /// assert!(Database::open(&dir).is_ok());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn open<P>(base_path: P) -> Result<Self, NariError>
+    where
+        P: AsRef<Path>,
+    {
+        Self::open_with(base_path, DatabaseOptions::default())
+    }
+    /// Opens the existing database at `base_path` like [`open`](Database::open), saved
+    /// with `options`, see [`DatabaseOptions`].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(path = %base_path.as_ref().display()), err)
+    )]
+    pub fn open_with<P>(base_path: P, options: DatabaseOptions) -> Result<Self, NariError>
+    where
+        P: AsRef<Path>,
+    {
+        let base_path = base_path.as_ref();
+        if !base_path.is_dir() {
+            return Err(NariError::NotFound(format!(
+                "database at {}",
+                base_path.display()
+            )));
+        }
+        let cache_name = cache_file_name(options.format);
+        if !base_path.join(&cache_name).is_file() {
+            return Err(NariError::NotFound(format!(
+                "event queue {}",
+                base_path.join(&cache_name).display()
+            )));
+        }
+        Ok(Self::at(base_path, &cache_name, &options))
+    }
+    /// Creates a new database representation just like [`new`](Database::new), but
+    /// saves the event queue in a file named `cache_name` inside of `base_path`.
+    ///
+    /// ### Usage
+    /// ```no_run
+    /// # use nari::models::Database;
+    /// # fn main() -> std::io::Result<()> {
+    /// let db = Database::with_cache_name("./db", "work_cache.ron")?;
+    /// assert!(db.cache_path().ends_with("work_cache.ron"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_cache_name<P>(base_path: P, cache_name: &str) -> Result<Self, NariError>
+    where
+        P: AsRef<Path>,
+    {
+        Self::create(base_path.as_ref(), cache_name, &DatabaseOptions::default())
+    }
+    /// Creates a new database representation just like [`new`](Database::new), but
+    /// saves users, events and the event queue in `format`.
+    ///
+    /// Files are named after the format, the event queue is saved as
+    /// `event_cache.json` for [`SerdeFormat::Json`] for example. Every file of a
+    /// database has to be in the same format, so changing the format of an existing
+    /// database requires exporting and importing its contents. Listeners have to be
+    /// told about the format with `with_format`, or created with `for_database`.
+    /// Use [`DatabaseOptions::format`] to combine it with other options.
+    ///
+    /// ### Usage
+    /// ```
+    /// # use nari::models::{Database, EventId, SerdeFormat, Timestamp};
+    /// # fn main() -> std::io::Result<()> {
+    /// # #[cfg(feature = "json")]
+    /// # {
+    /// let dir = std::env::temp_dir().join("nari_with_format");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::with_format(&dir, SerdeFormat::Json)?;
+    /// assert!(db.cache_path().ends_with("event_cache.json"));
+    ///
+    /// db.build_event(EventId(14), "Alice's Birthday", 123456789).build().save_to_db(&db)?;
+    /// assert!(dir.join("events/14.json").is_file());
+    /// assert_eq!(db.upcoming(Timestamp(0), 1)?[0].id, EventId(14));
+    /// # }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_format<P>(base_path: P, format: SerdeFormat) -> Result<Self, NariError>
     where
         P: AsRef<Path>,
     {
-        let base_path = base_path.as_ref().to_path_buf();
+        Self::with_options(base_path, DatabaseOptions::new().format(format))
+    }
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(path = %base_path.display(), cache_name, format = ?options.format), err)
+    )]
+    fn create(
+        base_path: &Path,
+        cache_name: &str,
+        options: &DatabaseOptions,
+    ) -> Result<Self, NariError> {
+        let base_path = base_path.to_path_buf();
         create_dir_all(base_path.join("users"))?;
         create_dir_all(base_path.join("events"))?;
-        if let Ok(f) = File::options()
+        if let Ok(mut f) = File::options()
             .write(true)
             .create_new(true)
-            .open(base_path.join("event_cache.ron"))
+            .open(base_path.join(cache_name))
         {
-            let buf = BufWriter::new(f);
-            let mut tree: BTreeMap<u64, u64> = BTreeMap::new();
-            tree.insert(u64::MAX, 0);
-            ron::ser::to_writer(buf, &tree).unwrap();
+            let file = Path::new(cache_name);
+            f.write_all(&encode(
+                &EventQueue::new(),
+                options.format,
+                options.codec,
+                file,
+            )?)?;
         }
-
-        Ok(Self { base_path })
+        let db = Self::at(&base_path, cache_name, options);
+        for dir in db.entry_dirs("events") {
+            create_dir_all(db.base_path.join(dir))?;
+        }
+        Ok(db)
     }
-    /// Creates and adds an user to the database, returns the created user.
+    fn at(base_path: &Path, cache_name: &str, options: &DatabaseOptions) -> Self {
+        Self {
+            base_path: base_path.to_path_buf(),
+            cache_name: String::from(cache_name),
+            lock_mode: LockMode::default(),
+            format: options.format,
+            codec: options.codec,
+            shards: options.shards,
+            layout: options.layout,
+            pretty: None,
+            max_events_per_user: None,
+            #[cfg(feature = "cache")]
+            hot_events: None,
+        }
+    }
+    /// Creates a new database representation just like [`new`](Database::new), but
+    /// encodes every file it writes with `codec`, including the event queue.
+    ///
+    /// Files are read in whichever encoding they were written, so compression can
+    /// be turned on or off for an existing database, and the files are converted as
+    /// they are written again. Listeners keep the encoding of the event queue. Only
+    /// available with the `compress` feature. Use [`DatabaseOptions::compression`] to
+    /// combine it with other options.
+    ///
+    /// ### Usage
+    /// ```
+    /// # use nari::models::{Codec, Database, EventId};
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_with_compression");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::with_compression(&dir, Codec::Gzip)?;
+    /// db.build_event(EventId(1), "Tea time", 1000).build().save_to_db(&db)?;
+    /// assert_eq!(db.fetch_event(EventId(1))?.unwrap().name, "Tea time");
+    ///
+    /// let bytes = std::fs::read(dir.join("events/1.ron"))?;
+    /// assert!(!String::from_utf8_lossy(&bytes).contains("Tea time"));
+    /// # std::fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "compress")]
+    pub fn with_compression<P>(base_path: P, codec: Codec) -> Result<Self, NariError>
+    where
+        P: AsRef<Path>,
+    {
+        Self::with_options(base_path, DatabaseOptions::new().compression(codec))
+    }
+    /// Creates a new database representation just like [`new`](Database::new), but
+    /// encrypts every file it writes with `key`, including the event queue, and
+    /// decrypts them when reading. Only available with the `encryption` feature.
+    ///
+    /// The files keep their extension, a header marks them as encrypted, and are
+    /// encrypted with XChaCha20-Poly1305 under a new random nonce on every write.
+    /// Each file is bound to where it is in the database, so files can't be swapped
+    /// with one another nor moved. Only files encrypted with the key are read, the ones
+    /// written before the key was given have to be encrypted with
+    /// [`encrypt_files`](Database::encrypt_files) first. Listeners have to be created
+    /// for the database, with
+    /// [`EventListener::for_database`](super::event::EventListener::for_database) or
+    /// the builder, to be able to read it.
+    ///
+    /// The key is never saved, **losing it means losing the data**, there is no way to
+    /// read the files back without it. Use [`DatabaseOptions::key`] to combine it with
+    /// other options.
+    ///
+    /// ### Usage
+    /// ```
+    /// # use nari::models::{Database, EventId};
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_with_key");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::with_key(&dir, [7; 32])?;
+    /// db.build_event(EventId(1), "Therapy session", 1000).build().save_to_db(&db)?;
+    /// assert_eq!(db.fetch_event(EventId(1))?.unwrap().name, "Therapy session");
+    ///
+    /// let bytes = std::fs::read(dir.join("events").join("1.ron"))?;
+    /// assert!(!String::from_utf8_lossy(&bytes).contains("Therapy"));
+    ///
+    /// let without_key = Database::new(&dir)?;
+    /// assert!(without_key.fetch_event(EventId(1)).is_err());
+    /// let wrong_key = Database::with_key(&dir, [8; 32])?;
+    /// assert!(wrong_key.fetch_event(EventId(1)).is_err());
+    /// # std::fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "encryption")]
+    pub fn with_key<P>(base_path: P, key: [u8; 32]) -> Result<Self, NariError>
+    where
+        P: AsRef<Path>,
+    {
+        Self::with_options(base_path, DatabaseOptions::new().key(key))
+    }
+    /// Creates a new database representation just like [`new`](Database::new), but
+    /// spreads the events over `shards` folders, saving each one as
+    /// `events/{id % shards}/{id}.ron`.
+    ///
+    /// Folders with hundreds of thousands of files are slow to list on some
+    /// filesystems, which every listing of the events and
+    /// [`rewrite_cache`](Database::rewrite_cache) do. By default, and with zero or one
+    /// shards, every event is saved in the `events` folder itself. Listeners have to be
+    /// told about the shards with `with_shards`, or created with `for_database`.
+    ///
+    /// The shards aren't recorded anywhere, so the database has to be opened with the
+    /// same number of them every time. To shard an existing database, or change the
+    /// number of shards, move every event file to the folder of its new shard and then
+    /// call [`rewrite_cache`](Database::rewrite_cache). Encrypted files can't be moved,
+    /// so encrypted databases are resharded by merging them into a new one with
+    /// [`merge_from`](Database::merge_from) instead. Snapshots keep the shards. Use
+    /// [`DatabaseOptions::shards`] to combine them with other options.
+    ///
+    /// ### Usage
+    /// ```
+    /// # use nari::models::{Database, EventId};
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_with_shards");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::with_shards(&dir, 16)?;
+    /// db.build_event(EventId(42), "Tea time", 1000).build().save_to_db(&db)?;
+    /// assert!(dir.join("events/10/42.ron").is_file());
+    /// assert_eq!(db.event_count()?, 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_shards<P>(base_path: P, shards: u64) -> Result<Self, NariError>
+    where
+        P: AsRef<Path>,
+    {
+        Self::with_options(base_path, DatabaseOptions::new().shards(shards))
+    }
+    /// Creates a new database representation just like [`new`](Database::new), but
+    /// keeping its users and events in the given [`Layout`].
+    ///
+    /// With [`Layout::Consolidated`] every event is in a single `events.ron` and
+    /// every user in a single `users.ron`, named after the format in use. Reading an
+    /// event opens one file no matter how many there are, which pays off when there
+    /// are many small events that are read far more often than they are written.
+    /// The tradeoff is write amplification: saving a single event reads and writes
+    /// back every other event with it, while holding a lock that stalls any other
+    /// writer, so it gets slower as the database grows. Sharding has no effect on it.
+    ///
+    /// The layout isn't saved in the database, every [`Database`] opening it has to
+    /// be created with the same one. Use [`consolidate`](Database::consolidate) to
+    /// move an existing database over, and [`DatabaseOptions::layout`] to combine it
+    /// with other options.
+    ///
+    /// ### Usage
+    /// ```
+    /// # use nari::models::{Database, EventId, Layout};
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_with_layout");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::with_layout(&dir, Layout::Consolidated)?;
+    /// db.build_event(EventId(1), "Tea time", 1000).build().save_to_db(&db)?;
+    /// db.build_event(EventId(2), "Dinner", 2000).build().save_to_db(&db)?;
+    /// assert!(dir.join("events.ron").is_file());
+    /// assert!(!dir.join("events/1.ron").exists());
+    /// assert_eq!(db.fetch_event(EventId(2))?.unwrap().name, "Dinner");
+    /// assert_eq!(db.event_count()?, 2);
+    /// # std::fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_layout<P>(base_path: P, layout: Layout) -> Result<Self, NariError>
+    where
+        P: AsRef<Path>,
+    {
+        Self::with_options(base_path, DatabaseOptions::new().layout(layout))
+    }
+    /// Sets how to wait for the locks taken while writing, by default it blocks until
+    /// they are free, see [`LockMode`].
     ///
     /// ## Usage
     /// ```no_run
-    /// # use nari::models::Database;
-    /// # use nari::models::UserId;
-    /// # fn main() {
-    /// # let db = Database::new("./db/").unwrap();
-    /// let alice = db.create_user(UserId(42), "Alice");
+    /// # use std::time::Duration;
+    /// # use nari::models::{Database, LockMode};
+    /// # fn main() -> std::io::Result<()> {
+    /// let db = Database::new("./db")?.with_lock_mode(LockMode::Retry {
+    ///     timeout: Duration::from_secs(2),
+    /// });
+    /// # Ok(())
     /// # }
     /// ```
-    pub fn create_user(&self, id: UserId, name: &str) -> User {
-        let user = User::new(id, name);
-        self.add_user(user.clone());
-        user
+    pub fn with_lock_mode(mut self, mode: LockMode) -> Self {
+        self.lock_mode = mode;
+        self
     }
-    /// Adds an already created user to the database, consumes the user.
+    /// Makes the database pretty print the users and events it writes, spread over
+    /// several indented lines, so that they are easier to read by a human.
+    ///
+    /// Pretty printed files are bigger and a bit slower to write, so by default they
+    /// are written compact, in a single line. Files are read either way, so this can be
+    /// turned on and off for an existing database. Only RON and JSON files can be
+    /// pretty printed, and the event queue, as well as the events a listener moves to
+    /// their next repetition, are always written compact.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{Database, EventId};
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_with_pretty");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::new(&dir)?.with_pretty(true);
+    /// db.build_event(EventId(14), "Tea time", 123456789).build().save_to_db(&db)?;
+    ///
+    /// let file = std::fs::read_to_string(dir.join("events/14.ron"))?;
+    /// assert!(file.lines().any(|line| line.trim() == "name: \"Tea time\","));
+    /// assert_eq!(db.fetch_event(EventId(14))?.unwrap().name, "Tea time");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_pretty(mut self, pretty: bool) -> Self {
+        self.pretty = pretty.then(PrettyConfig::default);
+        self
+    }
+    /// Pretty prints the users and events like [`with_pretty`](Database::with_pretty),
+    /// following `config`.
     ///
     /// ## Usage
     /// ```no_run
+    /// # use nari::models::{Database, PrettyConfig};
+    /// # fn main() -> std::io::Result<()> {
+    /// let config = PrettyConfig::new().indentor(String::from("  ")).struct_names(true);
+    /// let db = Database::new("./db")?.with_pretty_config(config);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_pretty_config(mut self, config: PrettyConfig) -> Self {
+        self.pretty = Some(config);
+        self
+    }
+    /// Limits how many events each user can be in, unlimited by default.
+    ///
+    /// Adding events to users, with [`add_event`](Database::add_event),
+    /// [`attach_event`](Database::attach_event),
+    /// [`add_event_to_users`](Database::add_event_to_users),
+    /// [`add_user_to_events`](Database::add_user_to_events),
+    /// [`add_events`](Database::add_events),
+    /// [`add_event_checked`](Database::add_event_checked),
+    /// [`add_event_async`](Database::add_event_async),
+    /// [`merge_from`](Database::merge_from) or
+    /// [`import_json`](Database::import_json), fails with
+    /// [`NariError::TooManyEvents`] when a user would go over it. They are all or
+    /// nothing, if any user would go over it nothing is saved, not even for the users
+    /// that would stay within it. Events a user already is in don't count twice, and
+    /// users already over it, like ones from before it was set, can't join more events
+    /// but keep the ones they have.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{Database, EventId, MergeStrategy, NariError, UserId};
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_max_events_per_user");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::new(&dir)?.with_max_events_per_user(1);
+    /// let alice = db.create_user(UserId(1), "Alice")?;
+    /// let bob = db.create_user(UserId(2), "Bob")?;
+    /// let lunch = db.build_event(EventId(1), "Lunch", 1000).build();
+    /// db.add_event_to_users(lunch, [alice.clone()])?;
+    ///
+    /// let alice = db.fetch_user(UserId(1))?.unwrap();
+    /// let dinner = db.build_event(EventId(2), "Dinner", 2000).build();
+    /// let err = db.add_event_to_users(dinner, [bob, alice]).err().unwrap();
+    /// assert!(matches!(err, NariError::TooManyEvents { user: UserId(1), max: 1 }));
+    ///
+    /// // Bob had room, but nothing was saved
+    /// assert_eq!(db.user_event_count(UserId(2))?, 0);
+    /// assert!(db.fetch_event(EventId(2))?.is_none());
+    ///
+    /// let breakfast = db.build_event(EventId(3), "Breakfast", 500).users([UserId(1)]);
+    /// let err = breakfast.build().save_to_db(&db).err().unwrap();
+    /// assert!(matches!(err, NariError::TooManyEvents { user: UserId(1), max: 1 }));
+    ///
+    /// let phone = Database::new(dir.join("phone"))?;
+    /// phone.create_user(UserId(3), "Carol")?;
+    /// let brunch = phone.build_event(EventId(4), "Brunch", 800).users([UserId(1)]);
+    /// brunch.build().save_to_db(&phone)?;
+    /// let err = db.merge_from(&phone, MergeStrategy::KeepOurs).err().unwrap();
+    /// assert!(matches!(err, NariError::TooManyEvents { user: UserId(1), max: 1 }));
+    /// assert!(db.fetch_user(UserId(3))?.is_none());
+    /// # std::fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_max_events_per_user(mut self, max: usize) -> Self {
+        self.max_events_per_user = Some(max);
+        self
+    }
+    /// Keeps up to `capacity` of the events fetched last in memory, so that fetching
+    /// them again doesn't read and parse their files. Once full, the event fetched the
+    /// longest ago is dropped. Only available with the `cache` feature.
+    ///
+    /// [`fetch_event`](Database::fetch_event) and
+    /// [`fetch_event_async`](Database::fetch_event_async) still check when the file of
+    /// the event was last modified, and read it again if it changed since it was
+    /// cached, so changes made by other processes or by listeners rescheduling the
+    /// events they fire are seen. Events written through the database are dropped from
+    /// it right away, which matters on filesystems that record modification times
+    /// with a coarse precision, where a change made by someone else within the same
+    /// tick may go unseen until the event is written again. With
+    /// [`Layout::Consolidated`] every write to any event changes the one file they all
+    /// share, so the cache only helps while no event is written.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{Database, EventId};
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_with_event_cache");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::new(&dir)?.with_event_cache(64);
+    /// db.build_event(EventId(1), "Tea time", 1000).build().save_to_db(&db)?;
+    /// assert_eq!(db.fetch_event(EventId(1))?.unwrap().name, "Tea time");
+    ///
+    /// // Changed by another process, which the cache notices
+    /// let other = Database::new(&dir)?;
+    /// let mut tea = other.fetch_event(EventId(1))?.unwrap();
+    /// tea.name = String::from("Coffee time");
+    /// other.update_event(tea)?;
+    /// assert_eq!(db.fetch_event(EventId(1))?.unwrap().name, "Coffee time");
+    ///
+    /// db.delete_event(EventId(1))?;
+    /// assert!(db.fetch_event(EventId(1))?.is_none());
+    /// # std::fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "cache")]
+    pub fn with_event_cache(mut self, capacity: usize) -> Self {
+        self.hot_events = Some(Mutex::new(EventLru::new(capacity)));
+        self
+    }
+    /// Returns how users and events are pretty printed, if they are.
+    #[cfg(feature = "async")]
+    pub(super) fn pretty(&self) -> Option<&PrettyConfig> {
+        self.pretty.as_ref()
+    }
+    /// Returns the format users, events and the event queue are saved in.
+    pub fn format(&self) -> SerdeFormat {
+        self.format
+    }
+    /// Returns how the files written by the database are encoded.
+    pub fn codec(&self) -> Codec {
+        self.codec
+    }
+    /// Returns how many folders the events are spread over, zero when they aren't,
+    /// see [`with_shards`](Database::with_shards).
+    pub fn shards(&self) -> u64 {
+        self.shards
+    }
+    /// Returns how users and events are kept on disk, see
+    /// [`with_layout`](Database::with_layout).
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+    /// Returns how the database waits for the locks taken while writing.
+    pub fn lock_mode(&self) -> LockMode {
+        self.lock_mode
+    }
+    /// Returns the folder the database was created in, holding every other file.
+    ///
+    /// ## Usage
+    /// ```
     /// # use nari::models::Database;
-    /// # use nari::models::UserId;
-    /// # use nari::models::User;
-    /// # fn main() {
-    /// # let db = Database::new("./db/").unwrap();
-    /// let alice = User::new(UserId(42), "Alice");
-    /// db.add_user(alice);
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_paths");
+    /// let db = Database::new(&dir)?;
+    /// assert_eq!(db.base_path(), dir);
+    /// assert_eq!(db.users_dir(), dir.join("users"));
+    /// assert_eq!(db.events_dir(), dir.join("events"));
+    /// assert_eq!(db.cache_path(), dir.join("event_cache.ron"));
+    /// # Ok(())
     /// # }
     /// ```
-    pub fn add_user(&self, user: User) {
-        let buf = self.open_buf_writer(user.id.0, "users").unwrap();
-        ron::ser::to_writer(buf, &user).unwrap();
+    pub fn base_path(&self) -> &Path {
+        &self.base_path
     }
-    /// Search for a user in the database, returns the user if found.
+    /// Returns the folder holding a file for each user.
+    pub fn users_dir(&self) -> PathBuf {
+        self.base_path.join("users")
+    }
+    /// Returns the folder holding a file for each event, or a folder for each shard
+    /// when using [`with_shards`](Database::with_shards).
+    pub fn events_dir(&self) -> PathBuf {
+        self.base_path.join("events")
+    }
+    /// Returns the path of the file holding the event queue.
+    pub fn cache_path(&self) -> PathBuf {
+        self.base_path.join(&self.cache_name)
+    }
+    /// Allocates an [`EventId`] that isn't used by any event in the database.
+    ///
+    /// Ids are handed out in increasing order and are never handed out twice by the
+    /// same database, even across threads or processes. Ids picked by hand are skipped.
     ///
     /// ## Usage
     /// ```no_run
     /// # use nari::models::Database;
-    /// use nari::models::UserId;
-    /// # fn main() {
-    /// # let db = Database::new("./db/").unwrap();
-    /// let alice = db.create_user(UserId(42), "Alice");
-    /// assert_eq!(alice, db.fetch_user(UserId(42)));
+    /// # fn main() -> std::io::Result<()> {
+    /// # let db = Database::new("./db/")?;
+    /// let id = db.next_event_id()?;
+    /// db.build_event(id, "Alice's Birthday", 123456789).build().save_to_db(&db)?;
+    /// assert_ne!(id, db.next_event_id()?);
+    /// # Ok(())
     /// # }
     /// ```
-    pub fn fetch_user(&self, id: UserId) -> User {
-        let buf = self.open_buf_reader(id.0, "users").unwrap();
-        ron::de::from_reader(buf).unwrap()
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, err))]
+    pub fn next_event_id(&self) -> Result<EventId, NariError> {
+        self.next_id::<Event, _>(|counters| &mut counters.events, "events")
+            .map(EventId)
     }
-
-    /// Returns an [`EventBuilder`], with the minimum information required.
+    /// Allocates a [`UserId`] that isn't used by any user in the database.
     ///
-    /// Takes an [`EventId`] which must represent an unique u64 value, the name for the event
-    /// and a u64 number representing an unix timestamp of when should it fire.
-    ///  
-    /// It doesn't add the event to the database until it is built and manually
-    /// added.
+    /// Works just like [`next_event_id`](Database::next_event_id).
+    ///
+    /// ## Usage
+    /// ```
+    /// # use std::collections::HashSet;
+    /// # use nari::models::Database;
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_next_user_id");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// Database::new(&dir)?;
+    /// // Each thread opens the database on its own, like separate processes would
+    /// let handles: Vec<_> = (0..8)
+    ///     .map(|_| {
+    ///         let dir = dir.clone();
+    ///         std::thread::spawn(move || {
+    ///             let db = Database::new(&dir).unwrap();
+    ///             (0..10).map(|_| db.next_user_id().unwrap()).collect::<Vec<_>>()
+    ///         })
+    ///     })
+    ///     .collect();
+    /// let ids: Vec<_> = handles.into_iter().flat_map(|h| h.join().unwrap()).collect();
+    /// let unique: HashSet<_> = ids.iter().collect();
+    /// assert_eq!(ids.len(), 80);
+    /// assert_eq!(unique.len(), 80);
+    /// # std::fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, err))]
+    pub fn next_user_id(&self) -> Result<UserId, NariError> {
+        self.next_id::<User, _>(|counters| &mut counters.users, "users")
+            .map(UserId)
+    }
+    /// Creates and adds an user to the database, returns the created user.
     ///
     /// ## Usage
     /// ```no_run
     /// # use nari::models::Database;
-    /// # use nari::models::EventId;
+    /// # use nari::models::UserId;
     /// # fn main() {
     /// # let db = Database::new("./db/").unwrap();
-    /// // We build a new event for Alice's birthday and manually add it to the database
-    /// let birthday = db.build_event(EventId(14), "Alice's Birthday", 123456789)
-    ///         .build()
-    ///         .save_to_db(&db);
+    /// let alice = db.create_user(UserId(42), "Alice").unwrap();
     /// # }
     /// ```
-    pub fn build_event(&self, id: EventId, name: &str, next_occurence: u64) -> EventBuilder {
-        EventBuilder::new(id, name, next_occurence)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(id = %id), err))]
+    pub fn create_user(&self, id: UserId, name: &str) -> Result<User, NariError> {
+        let user = User::new(id, name);
+        self.add_user(user.clone())?;
+        Ok(user)
     }
-    /// Adds an already created event to the database, consumes the event.
+    /// Adds an already created user to the database, consumes the user.
     ///
-    /// It adds it to the database automatically.
+    /// Returns an error if the user couldn't be written to the database.
     ///
     /// ## Usage
     /// ```no_run
     /// # use nari::models::Database;
     /// # use nari::models::UserId;
-    /// # use nari::models::EventId;
     /// # use nari::models::User;
-    /// # use nari::models::event::EventBuilder;
     /// # fn main() {
     /// # let db = Database::new("./db/").unwrap();
     /// let alice = User::new(UserId(42), "Alice");
-    /// let bob = User::new(UserId(43), "Bob");
-    /// let alices_birthday = EventBuilder::new(EventId(42), "Alice's Birthday", 123456789)
-    ///         .description("Today is Alice's birthday! 🎉")
-    ///         .users([alice.id, bob.id])
-    ///         .build();
-    /// db.add_event(alices_birthday);
+    /// db.add_user(alice).unwrap();
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(id = %user.id), err))]
+    pub fn add_user(&self, user: User) -> Result<(), NariError> {
+        self.write_entry(user.id.0, "users", &user)
+    }
+    /// Adds any amount of already created users to the database, consumes the users.
+    ///
+    /// Returns [`NariError::InvalidInput`] without adding any user if two of them have
+    /// the same id.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{Database, NariError, User, UserId};
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_add_users");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::new(&dir)?;
+    /// db.add_users((0..100).map(|i| User::new(UserId(i), &format!("User {i}"))))?;
+    /// assert_eq!(db.user_count()?, 100);
+    ///
+    /// let twins = [User::new(UserId(500), "Ann"), User::new(UserId(500), "Anna")];
+    /// let err = db.add_users(twins).unwrap_err();
+    /// assert!(matches!(err, NariError::InvalidInput(_)));
+    /// assert!(!db.contains_user(UserId(500)));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, err))]
+    pub fn add_users<I>(&self, users: I) -> Result<(), NariError>
+    where
+        I: IntoIterator<Item = User>,
+    {
+        let users: Vec<User> = users.into_iter().collect();
+        let mut ids = HashSet::with_capacity(users.len());
+        for user in users.iter() {
+            if !ids.insert(user.id) {
+                return Err(NariError::InvalidInput(format!(
+                    "user {} appears more than once",
+                    user.id
+                )));
+            }
+        }
+        for user in users {
+            self.write_entry(user.id.0, "users", &user)?;
+        }
+        Ok(())
+    }
+    /// Replaces a stored user with `user`, keeping the events of both.
+    ///
+    /// The events of the stored user are merged into the ones of `user` instead of
+    /// being replaced, so that saving a freshly created [`User`] doesn't take the user
+    /// out of its events. Use [`remove_event_from_user`](Database::remove_event_from_user)
+    /// to take a user out of an event.
+    ///
+    /// Returns [`NariError::NotFound`] if there is no user with the same id, use
+    /// [`add_user`](Database::add_user) to add it.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{Database, EventId, User, UserId};
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_update_user");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::new(&dir)?;
+    /// db.create_user(UserId(42), "Alice")?;
+    /// db.build_event(EventId(1), "Lunch", 1000)
+    ///     .users([UserId(42)])
+    ///     .build()
+    ///     .save_to_db(&db)?;
+    ///
+    /// db.update_user(User::new(UserId(42), "Alice Smith"))?;
+    /// let alice = db.fetch_user(UserId(42))?.unwrap();
+    /// assert_eq!(alice.name, "Alice Smith");
+    /// assert!(alice.events.contains(&EventId(1)));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(id = %user.id), err))]
+    pub fn update_user(&self, mut user: User) -> Result<(), NariError> {
+        let stored = self
+            .fetch_user(user.id)?
+            .ok_or_else(|| NariError::NotFound(format!("user {}", user.id)))?;
+        user.events.extend(stored.events);
+        self.write_entry(user.id.0, "users", &user)
+    }
+    /// Changes the name of a stored user, keeping everything else.
+    ///
+    /// Returns [`NariError::NotFound`] if there is no user with the given id.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{Database, EventId, UserId};
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_rename_user");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::new(&dir)?;
+    /// db.create_user(UserId(42), "Alice")?;
+    /// db.build_event(EventId(1), "Lunch", 1000)
+    ///     .users([UserId(42)])
+    ///     .build()
+    ///     .save_to_db(&db)?;
+    ///
+    /// db.rename_user(UserId(42), "Ally")?;
+    /// let ally = db.fetch_user(UserId(42))?.unwrap();
+    /// assert_eq!(ally.name, "Ally");
+    /// assert!(ally.events.contains(&EventId(1)));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(id = %id), err))]
+    pub fn rename_user(&self, id: UserId, new_name: &str) -> Result<(), NariError> {
+        let mut user = self
+            .fetch_user(id)?
+            .ok_or_else(|| NariError::NotFound(format!("user {id}")))?;
+        user.name = String::from(new_name);
+        self.write_entry(id.0, "users", &user)
+    }
+    /// Search for a user in the database, returns the user if found.
+    ///
+    /// Returns `Ok(None)` if there is no user with the given id, and
+    /// [`NariError::InvalidFile`] if its file could not be deserialized.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::Database;
+    /// use nari::models::UserId;
+    /// # fn main() {
+    /// # let db = Database::new("./db/").unwrap();
+    /// let alice = db.create_user(UserId(42), "Alice").unwrap();
+    /// assert_eq!(Some(alice), db.fetch_user(UserId(42)).unwrap());
+    /// assert_eq!(None, db.fetch_user(UserId(404)).unwrap());
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(id = %id), err))]
+    pub fn fetch_user(&self, id: UserId) -> Result<Option<User>, NariError> {
+        self.read_entry(id.0, "users")
+    }
+    /// Returns whether there is a user with the given id.
+    ///
+    /// Only the presence of its file is checked, the file may still be corrupt and
+    /// fail to be fetched.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::Database;
+    /// # use nari::models::UserId;
+    /// # fn main() -> std::io::Result<()> {
+    /// # let db = Database::new("./db/")?;
+    /// if !db.contains_user(UserId(42)) {
+    ///     db.create_user(UserId(42), "Alice")?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn contains_user(&self, id: UserId) -> bool {
+        self.entry_exists::<User>(id.0, "users")
+    }
+    /// Returns every user stored in the database, in no particular order.
+    ///
+    /// Unlike [`list_events`](Database::list_events), this fails as a whole if any
+    /// of the users can't be read.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::Database;
+    /// # use nari::models::UserId;
+    /// # fn main() -> std::io::Result<()> {
+    /// # let db = Database::new("./db/")?;
+    /// db.create_user(UserId(1), "Alice")?;
+    /// db.create_user(UserId(2), "Bob")?;
+    /// db.create_user(UserId(3), "Carol")?;
+    /// assert_eq!(db.list_users()?.len(), 3);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, err)
+    )]
+    pub fn list_users(&self) -> Result<Vec<User>, NariError> {
+        self.list_entries("users")?.collect()
+    }
+    /// Returns how many users are in the database, without reading them.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::Database;
+    /// # fn main() -> std::io::Result<()> {
+    /// # let db = Database::new("./db/")?;
+    /// println!("{} users", db.user_count()?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, err)
+    )]
+    pub fn user_count(&self) -> Result<usize, NariError> {
+        self.count_entries::<User>("users")
+    }
+    /// Returns how many events a user is in, or [`NariError::NotFound`] if the user
+    /// doesn't exist.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::{Database, UserId};
+    /// # fn main() -> std::io::Result<()> {
+    /// # let db = Database::new("./db/")?;
+    /// println!("Alice is in {} events", db.user_event_count(UserId(42))?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(id = %id), err)
+    )]
+    pub fn user_event_count(&self, id: UserId) -> Result<usize, NariError> {
+        self.fetch_user(id)?
+            .map(|user| user.events.len())
+            .ok_or_else(|| NariError::NotFound(format!("user {id}")))
+    }
+    /// Removes a user from the database, returns `Ok(false)` if there was no
+    /// user with the given id.
+    ///
+    /// It also removes the user from every [`Event`] it was in, events that no
+    /// longer exist are skipped.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::Database;
+    /// # use nari::models::{EventId, UserId};
+    /// # fn main() {
+    /// # let db = Database::new("./db/").unwrap();
+    /// let alice = db.create_user(UserId(42), "Alice").unwrap();
+    /// db.build_event(EventId(14), "Alice's Birthday", 123456789)
+    ///     .users([alice.id])
+    ///     .build()
+    ///     .save_to_db(&db)
+    ///     .unwrap();
+    ///
+    /// assert!(db.delete_user(UserId(42)).unwrap());
+    /// assert!(!db.delete_user(UserId(42)).unwrap());
+    /// let birthday = db.fetch_event(EventId(14)).unwrap().unwrap();
+    /// assert!(birthday.users.is_empty());
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(id = %id), err))]
+    pub fn delete_user(&self, id: UserId) -> Result<bool, NariError> {
+        let user: User = match self.read_entry(id.0, "users")? {
+            Some(user) => user,
+            None => return Ok(false),
+        };
+        for e in user.events.iter() {
+            if let Some(mut event) = self.fetch_event(*e)? {
+                event.users.remove(&id);
+                self.write_entry(event.id.0, "events", &event)?;
+            }
+        }
+        self.remove_entry::<User>(id.0, "users")?;
+        Ok(true)
+    }
+
+    /// Returns an [`EventBuilder`], with the minimum information required.
+    ///
+    /// Takes an [`EventId`] which must represent an unique u64 value, the name for the event
+    /// and a u64 number representing an unix timestamp of when should it fire.
+    ///  
+    /// It doesn't add the event to the database until it is built and manually
+    /// added.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::Database;
+    /// # use nari::models::EventId;
+    /// # fn main() {
+    /// # let db = Database::new("./db/").unwrap();
+    /// // We build a new event for Alice's birthday and manually add it to the database
+    /// let birthday = db.build_event(EventId(14), "Alice's Birthday", 123456789)
+    ///         .build()
+    ///         .save_to_db(&db)
+    ///         .unwrap();
+    /// # }
+    /// ```
+    pub fn build_event<T>(&self, id: EventId, name: &str, next_occurence: T) -> EventBuilder
+    where
+        T: Into<Timestamp>,
+    {
+        EventBuilder::new(id, name, next_occurence)
+    }
+    /// Returns an [`EventBuilder`] like [`build_event`](Database::build_event), but
+    /// reads when the event happens from text like `in 2 hours` or `tomorrow 9am`,
+    /// relative to the current time. See [`parse_time`](super::parse_time) for the
+    /// supported forms. Only available with the `natural` feature.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::{Database, EventId};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let db = Database::new("./db/")?;
+    /// db.build_event_at(EventId(14), "Stretch", "in 45 minutes")?
+    ///     .build()
+    ///     .save_to_db(&db)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "natural")]
+    pub fn build_event_at(
+        &self,
+        id: EventId,
+        name: &str,
+        when: &str,
+    ) -> Result<EventBuilder, ParseError> {
+        Ok(EventBuilder::new(
+            id,
+            name,
+            parse_time(when, Timestamp::now())?,
+        ))
+    }
+    /// Adds an already created event to the database, consumes the event.
+    ///
+    /// It adds it to the database automatically. If there already is an event with the
+    /// same id it gets replaced, use [`add_event_checked`](Database::add_event_checked)
+    /// to avoid that.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::Database;
+    /// # use nari::models::UserId;
+    /// # use nari::models::EventId;
+    /// # use nari::models::User;
+    /// # use nari::models::event::EventBuilder;
+    /// # fn main() -> std::io::Result<()> {
+    /// # let db = Database::new("./db/")?;
+    /// let alice = User::new(UserId(42), "Alice");
+    /// let bob = User::new(UserId(43), "Bob");
+    /// let alices_birthday = EventBuilder::new(EventId(42), "Alice's Birthday", 123456789)
+    ///         .description("Today is Alice's birthday! 🎉")
+    ///         .users([alice.id, bob.id])
+    ///         .build();
+    /// db.add_event(alices_birthday)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Events added from many threads at once all end up in the event queue:
+    /// ```
+    /// # use nari::models::{Database, EventId, Timestamp};
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_add_event_threads");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::new(&dir)?;
+    /// std::thread::scope(|s| {
+    ///     for thread in 0..16 {
+    ///         let db = &db;
+    ///         s.spawn(move || {
+    ///             for i in 0..25 {
+    ///                 let id = thread * 25 + i;
+    ///                 let event = db.build_event(EventId(id), "Ping", 1000 + id).build();
+    ///                 db.add_event(event).unwrap();
+    ///             }
+    ///         });
+    ///     }
+    /// });
+    ///
+    /// let queued = db.events_between(Timestamp(0), Timestamp(u64::MAX))?;
+    /// assert_eq!(queued.len(), 16 * 25);
+    /// # std::fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(id = %event.id), err))]
+    pub fn add_event(&self, event: Event) -> Result<(), NariError> {
+        self.insert_event(event)
+    }
+    /// Adds an already created event to the database just like
+    /// [`add_event`](Database::add_event), but returns [`NariError::AlreadyExists`]
+    /// instead of replacing an event with the same id.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::{Database, NariError};
+    /// # use nari::models::EventId;
+    /// # use nari::models::event::EventBuilder;
+    /// # fn main() {
+    /// # let db = Database::new("./db/").unwrap();
+    /// let birthday = EventBuilder::new(EventId(42), "Alice's Birthday", 123456789).build();
+    /// let meeting = EventBuilder::new(EventId(42), "A job meeting", 123456789).build();
+    /// db.add_event_checked(birthday).unwrap();
+    ///
+    /// let err = db.add_event_checked(meeting).unwrap_err();
+    /// assert!(matches!(err, NariError::AlreadyExists(_)));
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(id = %event.id), err))]
+    pub fn add_event_checked(&self, event: Event) -> Result<(), NariError> {
+        if self.entry_exists::<Event>(event.id.0, "events") {
+            return Err(NariError::AlreadyExists(format!("event {}", event.id)));
+        }
+        self.insert_event(event)
+    }
+    /// Adds any amount of already created events to the database, consumes the events.
+    ///
+    /// It behaves like calling [`add_event`](Database::add_event) for each event, but
+    /// updates the event queue and every user involved only once for the whole batch,
+    /// which is much faster when importing a lot of events.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::Database;
+    /// # use nari::models::{EventId, Timestamp, UserId};
+    /// # use nari::models::event::EventBuilder;
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_add_events");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::new(&dir)?;
+    /// let users = [UserId(1), UserId(2), UserId(3)];
+    /// for id in users {
+    ///     db.create_user(id, "Thirsty")?;
+    /// }
+    /// let reminders = (0..1000).map(|i| {
+    ///     EventBuilder::new(EventId(i), "Drink water", 123456789 + i * 60 * 60)
+    ///         .users([users[i as usize % 3]])
+    ///         .build()
+    /// });
+    /// db.add_events(reminders)?;
+    ///
+    /// // Every event is queued in order, and every user is in its events
+    /// let queued = db.upcoming(Timestamp(0), 2000)?;
+    /// assert_eq!(queued.len(), 1000);
+    /// assert!(queued.windows(2).all(|w| w[0].next_occurence <= w[1].next_occurence));
+    /// assert!(db.verify()?.is_clean());
+    /// let in_events: Vec<_> = users
+    ///     .iter()
+    ///     .map(|id| db.user_event_count(*id))
+    ///     .collect::<Result<_, _>>()?;
+    /// assert_eq!(in_events, [334, 333, 333]);
+    /// let first = db.fetch_user(UserId(1))?.unwrap();
+    /// assert!(first.events.contains(&EventId(999)));
+    /// # std::fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, err))]
+    pub fn add_events<I>(&self, events: I) -> Result<(), NariError>
+    where
+        I: IntoIterator<Item = Event>,
+    {
+        let events: Vec<Event> = events.into_iter().collect();
+        let users = self.join_events(events_by_user(&events), vec![])?;
+        let mut entries = vec![];
+        let mut ids = HashSet::new();
+        for event in events {
+            self.write_entry(event.id.0, "events", &event)?;
+            ids.insert(event.id.0);
+            entries.extend(event.queued_at().map(|at| (at, event.id.0)));
+        }
+        for user in users {
+            self.write_entry(user.id.0, "users", &user)?;
+        }
+        self.update_cache(|tree| {
+            tree.retain(|id| !ids.contains(&id));
+            tree.extend(entries);
+        })
+    }
+    /// Replaces a stored event with `event`, moving it in the event queue if its next
+    /// occurence changed, consumes the event.
+    ///
+    /// Unlike adding the event again, this makes sure the event is only queued at its
+    /// new occurence. The users of the event are saved as they are, see
+    /// [`add_event_to_users`](Database::add_event_to_users) to add users to it.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use std::time::Duration;
+    /// # use nari::models::{event::SyncEventListener, Database, EventId, Timestamp};
+    /// # use tokio_util::sync::CancellationToken;
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_update_event");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::new(&dir)?;
+    /// let now = Timestamp::now();
+    /// db.build_event(EventId(14), "Alice's Birthday", now + 1).build().save_to_db(&db)?;
+    /// db.build_event(EventId(15), "Bob's Birthday", now + 60 * 60).build().save_to_db(&db)?;
+    ///
+    /// // Alice's birthday moves forward, Bob's back
+    /// let mut alice = db.fetch_event(EventId(14))?.unwrap();
+    /// alice.next_occurence = now + 4;
+    /// db.update_event(alice)?;
+    /// let mut bob = db.fetch_event(EventId(15))?.unwrap();
+    /// bob.next_occurence = now + 2;
+    /// db.update_event(bob)?;
+    /// assert!(db.events_between(now + 1, now + 2)?.is_empty());
+    ///
+    /// // Each fires at its new time only
+    /// let (tx, rx) = std::sync::mpsc::channel();
+    /// let token = CancellationToken::new();
+    /// let handle = SyncEventListener::for_database(tx, 100, &db)
+    ///     .with_cancellation(token.clone())
+    ///     .spawn();
+    /// assert_eq!(rx.recv_timeout(Duration::from_secs(5)).unwrap().id, EventId(15));
+    /// assert!(Timestamp::now() >= now + 2);
+    /// assert_eq!(rx.recv_timeout(Duration::from_secs(5)).unwrap().id, EventId(14));
+    /// assert!(Timestamp::now() >= now + 4);
+    /// token.cancel();
+    /// handle.join().unwrap()?;
+    /// # std::fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(id = %event.id), err))]
+    pub fn update_event(&self, event: Event) -> Result<(), NariError> {
+        self.write_entry(event.id.0, "events", &event)?;
+        self.add_event_to_cache(&event)
+    }
+    /// Moves a stored event to `new_occurence`, keeping its duration, and queues it
+    /// there instead of at its previous occurence.
+    ///
+    /// Returns [`NariError::NotFound`] if there is no event with the given id.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use std::time::Duration;
+    /// # use nari::models::{event::SyncEventListener, Database, EventId, Timestamp};
+    /// # use tokio_util::sync::CancellationToken;
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_reschedule_event");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::new(&dir)?;
+    /// let now = Timestamp::now();
+    /// db.build_event(EventId(15), "Take a break", now + 1).build().save_to_db(&db)?;
+    /// db.build_event(EventId(16), "Stretch", now + 60 * 60).build().save_to_db(&db)?;
+    ///
+    /// // The break can wait, but stretching can't
+    /// db.reschedule_event(EventId(15), now + 4)?;
+    /// db.reschedule_event(EventId(16), now + 2)?;
+    /// assert!(db.events_between(now + 1, now + 2)?.is_empty());
+    ///
+    /// let (tx, rx) = std::sync::mpsc::channel();
+    /// let token = CancellationToken::new();
+    /// let handle = SyncEventListener::for_database(tx, 100, &db)
+    ///     .with_cancellation(token.clone())
+    ///     .spawn();
+    /// assert_eq!(rx.recv_timeout(Duration::from_secs(5)).unwrap().id, EventId(16));
+    /// assert!(Timestamp::now() >= now + 2);
+    /// assert_eq!(rx.recv_timeout(Duration::from_secs(5)).unwrap().id, EventId(15));
+    /// assert!(Timestamp::now() >= now + 4);
+    /// token.cancel();
+    /// handle.join().unwrap()?;
+    /// # std::fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(id = %id, new_occurence = %new_occurence), err)
+    )]
+    pub fn reschedule_event(&self, id: EventId, new_occurence: Timestamp) -> Result<(), NariError> {
+        let mut event = self
+            .fetch_event(id)?
+            .ok_or_else(|| NariError::NotFound(format!("event {id}")))?;
+        event.reschedule_to(new_occurence);
+        self.update_event(event)
+    }
+    /// Moves a stored event `delta_secs` seconds after its next occurence, keeping its
+    /// duration, like [`reschedule_event`](Database::reschedule_event) does with an
+    /// absolute time. Returns the new next occurence, which saturates at
+    /// `Timestamp(u64::MAX)`.
+    ///
+    /// Returns [`NariError::NotFound`] if there is no event with the given id.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{Database, EventId, Timestamp};
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_postpone_event");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::new(&dir)?;
+    /// db.build_event(EventId(1), "Call mom", 1000).build().save_to_db(&db)?;
+    ///
+    /// // Tomorrow then
+    /// let tomorrow = db.postpone_event(EventId(1), 24 * 60 * 60)?;
+    /// assert_eq!(tomorrow, Timestamp(1000 + 24 * 60 * 60));
+    /// assert!(db.events_between(Timestamp(1000), Timestamp(1001))?.is_empty());
+    /// assert_eq!(db.events_between(tomorrow, tomorrow + 1)?[0].id, EventId(1));
+    ///
+    /// assert_eq!(db.postpone_event(EventId(1), u64::MAX)?, Timestamp(u64::MAX));
+    /// # std::fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(id = %id, delta_secs), err))]
+    pub fn postpone_event(&self, id: EventId, delta_secs: u64) -> Result<Timestamp, NariError> {
+        let mut event = self
+            .fetch_event(id)?
+            .ok_or_else(|| NariError::NotFound(format!("event {id}")))?;
+        let new_occurence = event.next_occurence.saturating_add(delta_secs);
+        event.reschedule_to(new_occurence);
+        self.update_event(event)?;
+        Ok(new_occurence)
+    }
+    /// Pauses or resumes a stored event, paused events keep their file but are taken
+    /// out of the event queue, so they never fire and aren't found by
+    /// [`events_between`](Database::events_between) or [`upcoming`](Database::upcoming).
+    ///
+    /// Resuming a repeating event skips the occurences that passed while it was paused.
+    /// Returns [`NariError::NotFound`] if there is no event with the given id.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use std::time::{Duration, SystemTime, UNIX_EPOCH};
+    /// # use nari::models::{event::{Repeatability, SyncEventListener}, Database, EventId, Timestamp};
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_set_event_enabled");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::new(&dir)?;
+    /// let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    /// db.build_event(EventId(1), "Standup", now + 1)
+    ///     .repeats(Repeatability::Daily)
+    ///     .build()
+    ///     .save_to_db(&db)?;
+    ///
+    /// // Going on vacation
+    /// db.set_event_enabled(EventId(1), false)?;
+    /// let (tx, rx) = std::sync::mpsc::channel();
+    /// SyncEventListener::for_database(tx, 100, &db).spawn();
+    /// assert!(rx.recv_timeout(Duration::from_secs(2)).is_err());
+    ///
+    /// // Back to work, the missed standup is skipped
+    /// db.set_event_enabled(EventId(1), true)?;
+    /// let standup = db.fetch_event(EventId(1))?.unwrap();
+    /// assert_eq!(standup.next_occurence, now + 1 + 24 * 60 * 60);
+    /// assert_eq!(db.upcoming(Timestamp(now), 1)?, [standup]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(id = %id, enabled), err))]
+    pub fn set_event_enabled(&self, id: EventId, enabled: bool) -> Result<(), NariError> {
+        let mut event = self
+            .fetch_event(id)?
+            .ok_or_else(|| NariError::NotFound(format!("event {id}")))?;
+        if enabled && !event.enabled {
+            let now = Timestamp::now();
+            while event.next_occurence < now {
+                match event.next_repetition(event.next_occurence) {
+                    Some(next) => event.move_to(next),
+                    None => break,
+                }
+            }
+        }
+        event.enabled = enabled;
+        self.update_event(event)
+    }
+    /// Waits until the event with the given id fires and returns it, checking every
+    /// `refresh_rate` milliseconds without changing anything.
+    ///
+    /// The event counts as fired once the time it is queued at in the event queue has
+    /// passed, like a listener would fire it then, or once an
+    /// [`EventListener`](super::event::EventListener) delivered it and moved it to its
+    /// next repetition. Paused events and events that
+    /// [depend on](super::event::EventBuilder::depends_on) another one only count once a
+    /// listener delivered them, as listeners hold them back. Rescheduling the event
+    /// while waiting makes it wait for the new occurence instead.
+    ///
+    /// The event is read off the runtime's workers, with
+    /// [`fetch_event_async`](Database::fetch_event_async) when the `async` feature is on
+    /// and on tokio's blocking threads otherwise.
+    ///
+    /// Returns [`NariError::NotFound`] if there is no event with the given id, or it is
+    /// deleted while waiting.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use std::time::Duration;
+    /// # use nari::models::{Database, EventId, NariError};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let dir = std::env::temp_dir().join("nari_wait_for");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::new(&dir)?;
+    /// db.build_event(EventId(1), "Tea", 1000).build().save_to_db(&db)?;
+    ///
+    /// // Paused events never fire, however long ago they were due
+    /// db.set_event_enabled(EventId(1), false)?;
+    /// let waited = db.wait_for_timeout(EventId(1), 100, Duration::from_millis(500)).await;
+    /// assert!(matches!(waited, Err(NariError::WaitTimeout { .. })));
+    ///
+    /// db.set_event_enabled(EventId(1), true)?;
+    /// let tea = db.wait_for(EventId(1), 100).await?;
+    /// assert_eq!(tea.name, "Tea");
+    /// # std::fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(id = %id), err))]
+    pub async fn wait_for(&self, id: EventId, refresh_rate: u64) -> Result<Event, NariError> {
+        let refresh_rate = Duration::from_millis(clamp_refresh_rate(refresh_rate));
+        let mut fired_before = None;
+        loop {
+            let event = self
+                .fetch_event_off_thread(id)
+                .await?
+                .ok_or_else(|| NariError::NotFound(format!("event {id}")))?;
+            let fired = *fired_before.get_or_insert(event.occurrences_fired);
+            // Listeners hold events with a dependency back, so only their word counts
+            let due = event.depends_on.is_none()
+                && event.queued_at().is_some_and(|at| at <= Timestamp::now().0);
+            if due || event.occurrences_fired > fired {
+                return Ok(event);
+            }
+            tokio::time::sleep(refresh_rate).await;
+        }
+    }
+    // Reads the event without blocking the worker of the async caller
+    async fn fetch_event_off_thread(&self, id: EventId) -> Result<Option<Event>, NariError> {
+        #[cfg(feature = "async")]
+        return self.fetch_event_async(id).await;
+        #[cfg(not(feature = "async"))]
+        {
+            let reader = self.reader();
+            tokio::task::spawn_blocking(move || reader.fetch_event(id))
+                .await
+                .map_err(|e| NariError::Io(io::Error::other(e)))?
+        }
+    }
+    // A database reading the same files, without the cache of fetched events, to
+    // move to another thread
+    #[cfg(not(feature = "async"))]
+    fn reader(&self) -> Database {
+        Database {
+            base_path: self.base_path.clone(),
+            cache_name: self.cache_name.clone(),
+            lock_mode: self.lock_mode,
+            format: self.format,
+            codec: self.codec,
+            shards: self.shards,
+            layout: self.layout,
+            pretty: self.pretty.clone(),
+            max_events_per_user: self.max_events_per_user,
+            #[cfg(feature = "cache")]
+            hot_events: None,
+        }
+    }
+    /// Waits until the event with the given id fires like [`wait_for`](Database::wait_for)
+    /// does, giving up after `timeout` with [`NariError::WaitTimeout`].
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use nari::models::{Database, EventId, NariError};
+    /// # #[tokio::main]
+    /// # async fn main() -> std::io::Result<()> {
+    /// # let db = Database::new("./db/")?;
+    /// match db.wait_for_timeout(EventId(1), 500, Duration::from_secs(60)).await {
+    ///     Ok(tea) => println!("{} is ready", tea.name),
+    ///     Err(NariError::WaitTimeout { .. }) => println!("Still brewing"),
+    ///     Err(e) => return Err(e.into()),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(id = %id, ?timeout), err))]
+    pub async fn wait_for_timeout(
+        &self,
+        id: EventId,
+        refresh_rate: u64,
+        timeout: Duration,
+    ) -> Result<Event, NariError> {
+        match tokio::time::timeout(timeout, self.wait_for(id, refresh_rate)).await {
+            Ok(result) => result,
+            Err(_) => Err(NariError::WaitTimeout { id, timeout }),
+        }
+    }
+    /// Search for a event in the database, returns the event if found.
+    ///
+    /// Returns `Ok(None)` if there is no event with the given id, and
+    /// [`NariError::InvalidFile`], with the path of the file, if it exists but could not
+    /// be deserialized, e.g. because it was only partially written.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{Database, NariError};
+    /// # use nari::models::EventId;
+    /// # use nari::models::event::EventBuilder;
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_fetch_event");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::new(&dir)?;
+    /// let alices_birthday = EventBuilder::new(EventId(10), "Alice's Birthday", 123456789)
+    ///         .build();
+    /// db.add_event(alices_birthday.clone())?;
+    /// assert_eq!(Some(alices_birthday), db.fetch_event(EventId(10))?);
+    ///
+    /// // Events that were never stored are not an error
+    /// assert_eq!(None, db.fetch_event(EventId(404))?);
+    ///
+    /// // But corrupt ones are
+    /// std::fs::write(dir.join("events").join("7.ron"), "not ron at all")?;
+    /// let err = db.fetch_event(EventId(7)).err().unwrap();
+    /// assert!(matches!(err, NariError::InvalidFile { .. }));
+    /// # std::fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(id = %id), err))]
+    pub fn fetch_event(&self, id: EventId) -> Result<Option<Event>, NariError> {
+        #[cfg(feature = "cache")]
+        if self.caches_events() {
+            // Checked before reading, so that a change in between is only ever missed
+            // until the next fetch
+            let modified = fs::metadata(self.entry_file(id.0, "events")).and_then(|m| m.modified());
+            if let Ok(modified) = modified {
+                if let Some(event) = self.cached_event(id.0, modified) {
+                    return Ok(Some(event));
+                }
+                let event = self.read_entry(id.0, "events")?;
+                if let Some(event) = &event {
+                    self.cache_event(modified, event);
+                }
+                return Ok(event);
+            }
+        }
+        self.read_entry(id.0, "events")
+    }
+    /// Returns whether there is an event with the given id.
+    ///
+    /// Only the presence of its file is checked, the file may still be corrupt and
+    /// fail to be fetched.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::Database;
+    /// # use nari::models::EventId;
+    /// # fn main() -> std::io::Result<()> {
+    /// # let db = Database::new("./db/")?;
+    /// let event = db.build_event(EventId(42), "Party", 123456789).build();
+    /// if db.contains_event(EventId(42)) {
+    ///     db.update_event(event)?;
+    /// } else {
+    ///     db.add_event_checked(event)?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn contains_event(&self, id: EventId) -> bool {
+        self.entry_exists::<Event>(id.0, "events")
+    }
+    /// Returns the events with the given ids, in the same order as the ids.
+    ///
+    /// Ids without an event are skipped, while events that can't be read return an
+    /// error. Repeated ids return the event once per time they appear.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::Database;
+    /// # use nari::models::UserId;
+    /// # fn main() -> std::io::Result<()> {
+    /// # let db = Database::new("./db/")?;
+    /// if let Some(alice) = db.fetch_user(UserId(42))? {
+    ///     for event in db.fetch_events(alice.events)? {
+    ///         println!("{} at {}", event.name, event.next_occurence);
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, err)
+    )]
+    pub fn fetch_events<I>(&self, ids: I) -> Result<Vec<Event>, NariError>
+    where
+        I: IntoIterator<Item = EventId>,
+    {
+        let mut events = vec![];
+        for id in ids {
+            if let Some(event) = self.fetch_event(id)? {
+                events.push(event);
+            }
+        }
+        Ok(events)
+    }
+    /// Returns an iterator over every event stored in the database.
+    ///
+    /// Each event is read lazily as the iterator advances, so a corrupt file
+    /// only yields an error for that item instead of stopping the iteration.
+    /// The events are returned in no particular order.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::Database;
+    /// # fn main() -> std::io::Result<()> {
+    /// # let db = Database::new("./db/")?;
+    /// for event in db.list_events()? {
+    ///     match event {
+    ///         Ok(event) => println!("{} at {}", event.name, event.next_occurence),
+    ///         Err(e) => eprintln!("Skipping unreadable event: {e}"),
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, err)
+    )]
+    pub fn list_events(&self) -> Result<impl Iterator<Item = Result<Event, NariError>>, NariError> {
+        self.list_entries("events")
+    }
+    /// Returns how many events are in the database, without reading them.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::Database;
+    /// # fn main() -> std::io::Result<()> {
+    /// # let db = Database::new("./db/")?;
+    /// println!("{} events", db.event_count()?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, err)
+    )]
+    pub fn event_count(&self) -> Result<usize, NariError> {
+        self.count_entries::<Event>("events")
+    }
+    /// Removes every user and event, and empties the event queue, leaving the
+    /// database as [`new`](Database::new) creates it.
+    ///
+    /// Clearing an empty database does nothing. The ids handed out by
+    /// [`next_event_id`](Database::next_event_id) and
+    /// [`next_user_id`](Database::next_user_id) are not reused afterwards.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{Database, EventId, UserId};
+    /// # fn main() -> std::io::Result<()> {
+    /// let db = Database::new(std::env::temp_dir().join("nari_clear"))?;
+    /// db.create_user(UserId(1), "Alice")?;
+    /// db.build_event(EventId(1), "Tea time", 1000).build().save_to_db(&db)?;
+    ///
+    /// db.clear()?;
+    /// assert_eq!(db.user_count()?, 0);
+    /// assert_eq!(db.event_count()?, 0);
+    /// db.clear()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, err))]
+    pub fn clear(&self) -> Result<(), NariError> {
+        // Emptied first, so that the listener doesn't look for the removed events
+        self.write_cache(&EventQueue::new())?;
+        #[cfg(feature = "cache")]
+        if let Some(hot) = &self.hot_events {
+            hot.lock().unwrap_or_else(|e| e.into_inner()).clear();
+        }
+        for dir in self
+            .entry_dirs("events")
+            .into_iter()
+            .chain(self.entry_dirs("users"))
+        {
+            for entry in fs::read_dir(self.base_path.join(dir))? {
+                let path = entry?.path();
+                if path.is_file() {
+                    fs::remove_file(path)?;
+                }
+            }
+        }
+        for folder in ["events", "users"] {
+            match fs::remove_file(self.consolidated_path(folder)) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+    /// Copies every user, every event, the event queue and the id counters into `dest`,
+    /// which can then be opened as a database of its own.
+    ///
+    /// The event queue is locked for the whole copy, so events can't be added, moved
+    /// or fired by a listener in the meantime and the copy of the queue matches the
+    /// copied events. Users and events are always written whole, so a file is never
+    /// copied halfway through a write either way. Files already in `dest` with the same
+    /// name are replaced.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{Database, EventId, Timestamp, UserId};
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_snapshot");
+    /// let backup = std::env::temp_dir().join("nari_snapshot_backup");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// # let _ = std::fs::remove_dir_all(&backup);
+    /// let db = Database::new(&dir)?;
+    /// db.create_user(UserId(1), "Alice")?;
+    /// db.build_event(EventId(1), "Tea time", 1000).users([UserId(1)]).build().save_to_db(&db)?;
+    /// db.build_event(EventId(2), "Biscuits", 2000).build().save_to_db(&db)?;
+    ///
+    /// db.snapshot(&backup)?;
+    /// let copy = Database::new(&backup)?;
+    /// assert_eq!(copy.list_users()?, db.list_users()?);
+    /// assert_eq!(copy.events_between(Timestamp(0), Timestamp(3000))?, db.events_between(Timestamp(0), Timestamp(3000))?);
+    /// assert_eq!(copy.next_event_id()?, EventId(3));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(dest = %dest.as_ref().display()), err)
+    )]
+    pub fn snapshot<P>(&self, dest: P) -> Result<(), NariError>
+    where
+        P: AsRef<Path>,
+    {
+        let dest = dest.as_ref();
+        let _filelock = lock_cache(&self.cache_path(), self.lock_mode)?;
+        for dir in self
+            .entry_dirs("users")
+            .into_iter()
+            .chain(self.entry_dirs("events"))
+        {
+            create_dir_all(dest.join(&dir))?;
+            for entry in fs::read_dir(self.base_path.join(&dir))? {
+                let path = entry?.path();
+                if is_entry(&path, self.format) {
+                    // Entries deleted while copying are left out
+                    match fs::copy(&path, dest.join(&dir).join(path.file_name().unwrap())) {
+                        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+                        _ => {}
+                    }
+                }
+            }
+        }
+        for folder in ["users", "events"] {
+            let path = self.consolidated_path(folder);
+            if path.exists() {
+                let _lock = lock_consolidated(&path, self.lock_mode)?;
+                fs::copy(&path, dest.join(path.file_name().unwrap()))?;
+            }
+        }
+        let counters = self.base_path.join(ID_COUNTERS_FILE);
+        if counters.exists() {
+            let _lock = lock_file(&counters, || FileOptions::new().read(true), self.lock_mode)?;
+            fs::copy(&counters, dest.join(ID_COUNTERS_FILE))?;
+        }
+        fs::copy(self.cache_path(), dest.join(&self.cache_name))?;
+        Ok(())
+    }
+    /// Moves every user and event of the database into the consolidated layout, one
+    /// file for all the users and one for all the events, and switches the database
+    /// over to it, see [`with_layout`](Database::with_layout) for the tradeoff.
+    ///
+    /// The files of each user and event are removed once they are all copied over.
+    /// Nothing else may write to the database meanwhile, and listeners running on it
+    /// have to be created again from the database afterwards, as they keep reading
+    /// the removed files. Does nothing if the database is already consolidated.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{Database, EventId, Layout, UserId};
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_consolidate");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let mut db = Database::new(&dir)?;
+    /// db.create_user(UserId(1), "Alice")?;
+    /// db.build_event(EventId(1), "Tea time", 1000).users([UserId(1)]).build().save_to_db(&db)?;
+    ///
+    /// db.consolidate()?;
+    /// assert_eq!(db.layout(), Layout::Consolidated);
+    /// assert!(!dir.join("events/1.ron").exists());
+    /// assert!(dir.join("events.ron").is_file());
+    ///
+    /// let reopened = Database::with_layout(&dir, Layout::Consolidated)?;
+    /// assert_eq!(reopened.fetch_user(UserId(1))?.unwrap().name, "Alice");
+    /// assert_eq!(reopened.fetch_event(EventId(1))?.unwrap().name, "Tea time");
+    /// assert_eq!(reopened.next_event_id()?, EventId(2));
+    /// # std::fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, err))]
+    pub fn consolidate(&mut self) -> Result<(), NariError> {
+        if self.layout == Layout::Consolidated {
+            return Ok(());
+        }
+        let users = self.list_users()?;
+        let events = self.list_events()?.collect::<Result<Vec<_>, NariError>>()?;
+        let dirs = self
+            .entry_dirs("users")
+            .into_iter()
+            .chain(self.entry_dirs("events"))
+            .collect::<Vec<_>>();
+
+        self.update_consolidated("users", |entries: &mut BTreeMap<u64, User>| {
+            entries.extend(users.into_iter().map(|user| (user.id.0, user)));
+        })?;
+        self.update_consolidated("events", |entries: &mut BTreeMap<u64, Event>| {
+            entries.extend(events.into_iter().map(|event| (event.id.0, event)));
+        })?;
+        for dir in dirs {
+            for entry in fs::read_dir(self.base_path.join(dir))? {
+                let path = entry?.path();
+                if is_entry(&path, self.format) {
+                    fs::remove_file(path)?;
+                }
+            }
+        }
+        self.layout = Layout::Consolidated;
+        #[cfg(feature = "cache")]
+        if let Some(hot) = &self.hot_events {
+            hot.lock().unwrap_or_else(|e| e.into_inner()).clear();
+        }
+        Ok(())
+    }
+    /// Encrypts every file written before the database was given a key with it, returns
+    /// how many were encrypted. Only available with the `encryption` feature.
+    ///
+    /// A database opened with [`with_key`](Database::with_key) only reads the files
+    /// encrypted with the key, so this has to be called once before using an existing
+    /// database with a new key. Files already encrypted with it are left as they are,
+    /// and files encrypted with another key make it fail. Nothing else may write to the
+    /// database meanwhile, like with [`consolidate`](Database::consolidate). Does
+    /// nothing without a key.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{Database, EventId};
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_encrypt_files");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let plain = Database::new(&dir)?;
+    /// plain.build_event(EventId(1), "Therapy session", 1000).build().save_to_db(&plain)?;
+    ///
+    /// let db = Database::with_key(&dir, [7; 32])?;
+    /// assert!(db.fetch_event(EventId(1)).is_err());
+    /// assert_eq!(db.encrypt_files()?, 2);
+    /// assert_eq!(db.fetch_event(EventId(1))?.unwrap().name, "Therapy session");
+    /// assert_eq!(db.encrypt_files()?, 0);
+    ///
+    /// // Files are bound to where they are
+    /// db.build_event(EventId(2), "Dentist", 2000).build().save_to_db(&db)?;
+    /// std::fs::copy(dir.join("events/2.ron"), dir.join("events/1.ron"))?;
+    /// assert!(db.fetch_event(EventId(1)).is_err());
+    /// # std::fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "encryption")]
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, err))]
+    pub fn encrypt_files(&self) -> Result<usize, NariError> {
+        let Codec::Encrypted(key) = self.codec else {
+            return Ok(0);
+        };
+        // Returns whether the file at `path` had to be encrypted
+        let encrypt = |path: &Path| -> Result<bool, NariError> {
+            let bytes = match fs::read(path) {
+                Ok(bytes) => bytes,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+                Err(e) => return Err(e.into()),
+            };
+            let file = file_in(&self.base_path, path);
+            match encrypt_plain(&bytes, self.format, key, file).map_err(|e| e.in_file(path))? {
+                Some(bytes) => write_bytes_atomically(path, &bytes).map(|()| true),
+                None => Ok(false),
+            }
+        };
+        let _filelock = lock_cache(&self.cache_path(), self.lock_mode)?;
+        let mut encrypted = usize::from(encrypt(&self.cache_path())?);
+        for folder in ["users", "events"] {
+            for dir in self.entry_dirs(folder) {
+                for entry in fs::read_dir(self.base_path.join(dir))? {
+                    let path = entry?.path();
+                    if is_entry(&path, self.format) && encrypt(&path)? {
+                        encrypted += 1;
+                    }
+                }
+            }
+            let path = self.consolidated_path(folder);
+            if path.exists() {
+                let _lock = lock_consolidated(&path, self.lock_mode)?;
+                encrypted += usize::from(encrypt(&path)?);
+            }
+        }
+        Ok(encrypted)
+    }
+    /// Returns every event in the database as they are at a single point in time,
+    /// sorted by id.
+    ///
+    /// Unlike with [`list_events`](Database::list_events), the event queue is locked
+    /// while the events are read, like [`snapshot`](Database::snapshot) does, so the
+    /// queue can't be rewritten by a database or a listener in the meantime, and the
+    /// events read are the ones it holds. Each file is always written whole, so no
+    /// event is read halfway through a write either.
+    ///
+    /// It is consistent with the event queue, not with every file: a write that
+    /// already updated the queue can still be writing its event file, which is then
+    /// read either before or after it. Files edited by hand or by other tools, which
+    /// don't take the lock, aren't kept out at all.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{Database, EventId};
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_snapshot_events");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::new(&dir)?;
+    /// for id in [3, 1, 2] {
+    ///     db.build_event(EventId(id), "Reminder", 1000).build().save_to_db(&db)?;
+    /// }
+    ///
+    /// let ids: Vec<_> = db.snapshot_events()?.iter().map(|e| e.id).collect();
+    /// assert_eq!(ids, [EventId(1), EventId(2), EventId(3)]);
+    /// # std::fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, err)
+    )]
+    pub fn snapshot_events(&self) -> Result<Vec<Event>, NariError> {
+        let _filelock = lock_cache(&self.cache_path(), self.lock_mode)?;
+        let mut events = self.list_events()?.collect::<Result<Vec<_>, NariError>>()?;
+        events.sort_by_key(|e| e.id.0);
+        Ok(events)
+    }
+    /// Adds the users and events of `other` to the database, deciding with `strategy`
+    /// which event to keep when both have a different one with the same id.
+    ///
+    /// The events taken from `other` are added like [`add_event`](Database::add_event)
+    /// does, linking them to their users, and an overwritten event keeps the users of
+    /// both. Fails with [`NariError::TooManyEvents`] without merging anything if that
+    /// puts a user over [`with_max_events_per_user`](Database::with_max_events_per_user).
+    ///
+    /// Users found in both only have their name merged, which follows `strategy` like
+    /// events do, except that only [`KeepTheirs`](MergeStrategy::KeepTheirs) takes the
+    /// name from `other`. The event queue is rebuilt afterwards with
+    /// [`rewrite_cache`](Database::rewrite_cache).
+    ///
+    /// Merging the same database again changes nothing, everything is skipped.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{event::Priority, Database, EventId, MergeStrategy, UserId};
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_merge_from");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let laptop = Database::new(dir.join("laptop"))?;
+    /// let phone = Database::new(dir.join("phone"))?;
+    /// laptop.build_event(EventId(1), "Dentist", 1000).build().save_to_db(&laptop)?;
+    /// phone.build_event(EventId(1), "Dentist", 1000)
+    ///     .priority(Priority::High)
+    ///     .build()
+    ///     .save_to_db(&phone)?;
+    /// let swole = phone.create_user(UserId(1), "Swole")?;
+    /// let gym = phone.build_event(EventId(2), "Gym", 2000).build();
+    /// phone.add_event_to_users(gym, [swole])?;
+    ///
+    /// let report = laptop.merge_from(&phone, MergeStrategy::HighestPriority)?;
+    /// assert_eq!((report.events_added, report.events_overwritten), (1, 1));
+    /// assert_eq!(laptop.fetch_event(EventId(1))?.unwrap().priority, Priority::High);
+    /// assert!(laptop.fetch_user(UserId(1))?.unwrap().events.contains(&EventId(2)));
+    ///
+    /// let again = laptop.merge_from(&phone, MergeStrategy::HighestPriority)?;
+    /// assert_eq!(again.events_skipped, 2);
+    ///
+    /// // Users linked on either side are kept, so merging again still skips the event
+    /// let mara = laptop.create_user(UserId(2), "Mara")?;
+    /// laptop.add_event_to_users(laptop.build_event(EventId(3), "Run", 3000).build(), [mara])?;
+    /// let swole = phone.fetch_user(UserId(1))?.unwrap();
+    /// phone.add_event_to_users(phone.build_event(EventId(3), "Run", 3000).build(), [swole])?;
+    /// let report = laptop.merge_from(&phone, MergeStrategy::KeepTheirs)?;
+    /// assert_eq!(report.events_overwritten, 1);
+    /// assert_eq!(laptop.fetch_event(EventId(3))?.unwrap().users.len(), 2);
+    /// let again = laptop.merge_from(&phone, MergeStrategy::KeepTheirs)?;
+    /// assert_eq!((again.events_overwritten, again.events_skipped), (0, 3));
+    /// # std::fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, err))]
+    pub fn merge_from(
+        &self,
+        other: &Database,
+        strategy: MergeStrategy,
+    ) -> Result<MergeReport, NariError> {
+        let mut report = MergeReport::default();
+        let mut users = vec![];
+        for theirs in other.list_users()? {
+            // Linked to their events as those are merged below
+            let Some(ours) = self.fetch_user(theirs.id)? else {
+                let mut added = theirs;
+                added.events.clear();
+                users.push(added);
+                report.users_added += 1;
+                continue;
+            };
+            let mut merged = ours.clone();
+            if strategy == MergeStrategy::KeepTheirs {
+                merged.name = theirs.name;
+            }
+            if same_user(&merged, &ours) {
+                report.users_skipped += 1;
+            } else {
+                users.push(merged);
+                report.users_overwritten += 1;
+            }
+        }
+        let mut events = vec![];
+        for theirs in other.list_events()? {
+            let theirs = theirs?;
+            let Some(ours) = self.fetch_event(theirs.id)? else {
+                events.push(theirs);
+                report.events_added += 1;
+                continue;
+            };
+            // Compared with the users of both, as an earlier merge already joined them
+            let mut merged = theirs;
+            merged.users.extend(ours.users.iter().copied());
+            let keep_theirs = !same_event(&ours, &merged)
+                && match strategy {
+                    MergeStrategy::KeepOurs => false,
+                    MergeStrategy::KeepTheirs => true,
+                    MergeStrategy::LatestOccurence => merged.next_occurence > ours.next_occurence,
+                    MergeStrategy::HighestPriority => merged.priority > ours.priority,
+                };
+            if keep_theirs {
+                events.push(merged);
+                report.events_overwritten += 1;
+            } else {
+                report.events_skipped += 1;
+            }
+        }
+        // Nothing is written until every user is checked against the cap
+        let users = self.join_events(events_by_user(&events), users)?;
+        for user in users {
+            self.write_entry(user.id.0, "users", &user)?;
+        }
+        for event in events {
+            self.write_entry(event.id.0, "events", &event)?;
+        }
+        self.rewrite_cache()?;
+        Ok(report)
+    }
+    /// Returns the events scheduled from `start` (inclusive) up to `end` (exclusive),
+    /// sorted by their next occurence.
+    ///
+    /// It uses the event queue to find the events, so only the events that are
+    /// still pending are taken into account, by when a listener delivers them next,
+    /// which is ahead of the occurence for events with a
+    /// [`lead_secs`](Event::lead_secs). Returns an empty list if `start` is not
+    /// before `end`.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::{Database, Timestamp};
+    /// # fn main() -> std::io::Result<()> {
+    /// # let db = Database::new("./db/")?;
+    /// # let now = Timestamp(1678000000);
+    /// let this_week = db.events_between(now, now + 7 * 24 * 60 * 60)?;
+    /// for event in this_week {
+    ///     println!("{} at {}", event.name, event.next_occurence);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(start = %start, end = %end), err)
+    )]
+    pub fn events_between(
+        &self,
+        start: Timestamp,
+        end: Timestamp,
+    ) -> Result<Vec<Event>, NariError> {
+        if start >= end {
+            return Ok(vec![]);
+        }
+        let tree = self.read_cache()?;
+        self.fetch_events(tree.range(start.0..end.0).map(|(_, id)| EventId(id)))
+    }
+    /// Returns up to `limit` events scheduled from `now` on, sorted
+    /// by their next occurence in ascending order.
+    ///
+    /// Like [`events_between`](Database::events_between), it only takes into account the
+    /// events still pending in the event queue, and changes nothing.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{Database, EventId, Timestamp};
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_upcoming");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::new(&dir)?;
+    /// for (id, at) in [(1, 3000), (2, 1000), (3, 2000), (4, 500)] {
+    ///     db.build_event(EventId(id), "Reminder", at).build().save_to_db(&db)?;
+    /// }
+    ///
+    /// let next: Vec<_> = db.upcoming(Timestamp(1000), 2)?.iter().map(|e| e.id).collect();
+    /// assert_eq!(next, [EventId(2), EventId(3)]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(now = %now, limit), err)
+    )]
+    pub fn upcoming(&self, now: Timestamp, limit: usize) -> Result<Vec<Event>, NariError> {
+        let tree = self.read_cache()?;
+        let ids = tree.range(now.0..).take(limit).map(|(_, id)| EventId(id));
+        self.fetch_events(ids)
+    }
+    /// Returns every event tagged with `tag`, sorted by id.
+    ///
+    /// There is no index of the tags, so every event file is read to find them and
+    /// the cost grows linearly with the size of the whole database.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::Database;
+    /// # fn main() -> std::io::Result<()> {
+    /// # let db = Database::new("./db/")?;
+    /// for event in db.events_with_tag("work")? {
+    ///     println!("{} at {}", event.name, event.next_occurence);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, err)
+    )]
+    pub fn events_with_tag(&self, tag: &str) -> Result<Vec<Event>, NariError> {
+        let mut events = self.filter_events(|e| e.tags.contains(tag))?;
+        events.sort_by_key(|e| e.id.0);
+        Ok(events)
+    }
+    /// Returns every event whose name or description contains `query`, ignoring
+    /// case, sorted by their next occurence.
+    ///
+    /// Both sides are lowercased with [`str::to_lowercase`], so accented and other
+    /// non ASCII letters match regardless of case, but accents are kept, `"reunion"`
+    /// doesn't match `"Réunion"`. Like [`events_with_tag`](Database::events_with_tag)
+    /// every event file is read to find the matches.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{Database, EventId};
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_search_events");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::new(&dir)?;
+    /// db.build_event(EventId(1), "Réunion d'équipe", 2000).build().save_to_db(&db)?;
+    /// db.build_event(EventId(2), "Lunch", 1000)
+    ///     .description("After the ÉQUIPE meeting")
+    ///     .build()
+    ///     .save_to_db(&db)?;
+    ///
+    /// let found = db.search_events("équipe")?;
+    /// assert_eq!(found.iter().map(|e| e.id).collect::<Vec<_>>(), [EventId(2), EventId(1)]);
+    /// assert_eq!(db.search_events("RÉUNION")?.len(), 1);
+    /// assert!(db.search_events("reunion")?.is_empty());
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, err)
+    )]
+    pub fn search_events(&self, query: &str) -> Result<Vec<Event>, NariError> {
+        let query = query.to_lowercase();
+        let mut events = self.filter_events(|e| {
+            e.name.to_lowercase().contains(&query) || e.description.to_lowercase().contains(&query)
+        })?;
+        events.sort_by_key(|e| (e.next_occurence, e.id.0));
+        Ok(events)
+    }
+    /// Returns every event with a priority of at least `min`, the most important first.
+    ///
+    /// Events are sorted by priority from [`Urgent`](Priority::Urgent) down to `min`,
+    /// and events with the same priority by their next occurence, the soonest first.
+    /// Like [`events_with_tag`](Database::events_with_tag) every event file is read to
+    /// find them.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{event::Priority, Database, EventId};
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_events_at_least");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::new(&dir)?;
+    /// db.build_event(EventId(1), "Water the plants", 1000).build().save_to_db(&db)?;
+    /// db.build_event(EventId(2), "Renew passport", 3000)
+    ///     .priority(Priority::VeryHigh)
+    ///     .build()
+    ///     .save_to_db(&db)?;
+    /// db.build_event(EventId(3), "Server is down", 5000)
+    ///     .priority(Priority::Urgent)
+    ///     .build()
+    ///     .save_to_db(&db)?;
+    /// db.build_event(EventId(4), "Pay rent", 2000)
+    ///     .priority(Priority::VeryHigh)
+    ///     .build()
+    ///     .save_to_db(&db)?;
+    ///
+    /// let triage = db.events_at_least(Priority::VeryHigh)?;
+    /// let ids: Vec<_> = triage.iter().map(|e| e.id).collect();
+    /// assert_eq!(ids, [EventId(3), EventId(4), EventId(2)]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(?min), err))]
+    pub fn events_at_least(&self, min: Priority) -> Result<Vec<Event>, NariError> {
+        let mut events = self.filter_events(|e| e.priority >= min)?;
+        events.sort_by(|a, b| {
+            b.priority
+                .cmp(&a.priority)
+                .then(a.next_occurence.cmp(&b.next_occurence))
+                .then(a.id.0.cmp(&b.id.0))
+        });
+        Ok(events)
+    }
+    /// Raises the priority of every event whose next occurence is at least `max_age`
+    /// seconds before `now`, a [`Timestamp`] or unix timestamp, by one step, returns
+    /// how many events were raised.
+    ///
+    /// Nothing is aged unless this is called, and each call raises the priority by a
+    /// single step as described in [`Priority::raised`], so events that keep being
+    /// put off climb one step per call until they are [`Urgent`](Priority::Urgent).
+    /// Calling it once per aging period, e.g. once a day, makes overdue events rise
+    /// one step per period. Like [`events_with_tag`](Database::events_with_tag) every
+    /// event file is read.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{event::Priority, Database, EventId, Timestamp};
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_age_events");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::new(&dir)?;
+    /// const DAY: u64 = 24 * 60 * 60;
+    /// let now = Timestamp(100 * DAY);
+    /// db.build_event(EventId(1), "Clean the garage", now - 30 * DAY).build().save_to_db(&db)?;
+    /// db.build_event(EventId(2), "Buy milk", now - 1000).build().save_to_db(&db)?;
+    ///
+    /// assert_eq!(db.age_events(now, 7 * DAY)?, 1);
+    /// assert_eq!(db.fetch_event(EventId(1))?.unwrap().priority, Priority::Medium);
+    /// assert_eq!(db.fetch_event(EventId(2))?.unwrap().priority, Priority::Low);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(now, max_age), err)
+    )]
+    pub fn age_events<T>(&self, now: T, max_age: u64) -> Result<usize, NariError>
+    where
+        T: Into<Timestamp>,
+    {
+        let now = now.into();
+        let old = self.filter_events(|e| {
+            e.priority != Priority::Urgent && e.next_occurence.saturating_add(max_age) <= now
+        })?;
+        let aged = old.len();
+        for mut event in old {
+            event.priority = event.priority.raised();
+            self.write_entry(event.id.0, "events", &event)?;
+        }
+        Ok(aged)
+    }
+    /// Returns how many events there are by priority and by repeatability, along with
+    /// the earliest and latest next occurence, reading every event only once.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{event::{Priority, Repeatability}, Database, EventId, Timestamp};
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_stats");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::new(&dir)?;
+    /// db.build_event(EventId(1), "Standup", 1000)
+    ///     .repeats(Repeatability::Daily)
+    ///     .build()
+    ///     .save_to_db(&db)?;
+    /// db.build_event(EventId(2), "Deadline", 5000)
+    ///     .priority(Priority::Urgent)
+    ///     .build()
+    ///     .save_to_db(&db)?;
+    ///
+    /// let stats = db.stats()?;
+    /// assert_eq!(stats.total, 2);
+    /// assert_eq!(stats.by_priority[&Priority::Urgent], 1);
+    /// assert_eq!(stats.by_repeatability["daily"], 1);
+    /// assert_eq!((stats.earliest, stats.latest), (Some(Timestamp(1000)), Some(Timestamp(5000))));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, err)
+    )]
+    pub fn stats(&self) -> Result<DatabaseStats, NariError> {
+        let mut stats = DatabaseStats::default();
+        for event in self.list_events()? {
+            let event = event?;
+            stats.total += 1;
+            *stats.by_priority.entry(event.priority).or_default() += 1;
+            *stats
+                .by_repeatability
+                .entry(event.repeats.to_string())
+                .or_default() += 1;
+            let at = event.next_occurence;
+            stats.earliest = Some(stats.earliest.map_or(at, |earliest| earliest.min(at)));
+            stats.latest = Some(stats.latest.map_or(at, |latest| latest.max(at)));
+        }
+        Ok(stats)
+    }
+    /// Returns every event the given user is in, in no particular order.
+    ///
+    /// Events the user references but that no longer exist are skipped, while
+    /// events that can't be read return an error. If the user doesn't exist
+    /// [`NariError::NotFound`] is returned.
+    ///
+    /// Each event is read from its own file one after the other, so the cost
+    /// grows linearly with the amount of events the user is in.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::Database;
+    /// # use nari::models::{EventId, UserId};
+    /// # fn main() -> std::io::Result<()> {
+    /// # let db = Database::new("./db/")?;
+    /// let alice = db.create_user(UserId(42), "Alice")?;
+    /// db.build_event(EventId(14), "Alice's Birthday", 123456789)
+    ///     .users([alice.id])
+    ///     .build()
+    ///     .save_to_db(&db)?;
+    ///
+    /// let events = db.events_for_user(alice.id)?;
+    /// assert_eq!(events[0].id, EventId(14));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(id = %id), err))]
+    pub fn events_for_user(&self, id: UserId) -> Result<Vec<Event>, NariError> {
+        let user = self
+            .fetch_user(id)?
+            .ok_or_else(|| NariError::NotFound(format!("user {id}")))?;
+        self.fetch_events(user.events)
+    }
+    /// Removes an event from the database, returns `Ok(false)` if there was no
+    /// event with the given id.
+    ///
+    /// It also removes the event from the event queue and from every [`User`]
+    /// that referenced it. Other events sharing the same timestamp are left untouched.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{Database, EventId, Timestamp};
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_delete_event");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::new(&dir)?;
+    /// db.build_event(EventId(20), "Lunch", 123456789).build().save_to_db(&db)?;
+    /// db.build_event(EventId(21), "Dinner", 123456789).build().save_to_db(&db)?;
+    ///
+    /// assert!(db.delete_event(EventId(20))?);
+    /// assert!(!db.delete_event(EventId(20))?);
+    /// assert_eq!(None, db.fetch_event(EventId(20))?);
+    ///
+    /// // Dinner is still queued at the same time
+    /// let queued = db.events_between(Timestamp(123456789), Timestamp(123456790))?;
+    /// assert_eq!(queued.iter().map(|e| e.id).collect::<Vec<_>>(), [EventId(21)]);
+    /// # std::fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(id = %id), err))]
+    pub fn delete_event(&self, id: EventId) -> Result<bool, NariError> {
+        let event: Event = match self.read_entry(id.0, "events")? {
+            Some(event) => event,
+            None => return Ok(false),
+        };
+        self.update_cache(|tree| {
+            if !tree.remove(event.reminder_at(), id.0) {
+                tree.remove_id(id.0);
+            }
+        })?;
+        for u in event.users.iter() {
+            if let Some(mut user) = self.fetch_user(*u)? {
+                user.events.remove(&id);
+                self.write_entry(user.id.0, "users", &user)?;
+            }
+        }
+        self.remove_entry::<Event>(id.0, "events")?;
+        Ok(true)
+    }
+    /// Gives the event `old` the id `new`, moving its file and its place in the event
+    /// queue, and replacing `old` with `new` in the events of every user in it.
+    ///
+    /// Returns [`NariError::NotFound`] if there is no event with the id `old`, and
+    /// [`NariError::AlreadyExists`] without changing anything if there is already an
+    /// event with the id `new`.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{Database, EventId, NariError, Timestamp, UserId};
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_change_event_id");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::new(&dir)?;
+    /// db.create_user(UserId(1), "Alice")?;
+    /// db.build_event(EventId(1), "Lunch", 1000).users([UserId(1)]).build().save_to_db(&db)?;
+    /// db.build_event(EventId(2), "Dinner", 2000).build().save_to_db(&db)?;
+    ///
+    /// let err = db.change_event_id(EventId(1), EventId(2)).unwrap_err();
+    /// assert!(matches!(err, NariError::AlreadyExists(_)));
+    /// assert_eq!(db.fetch_event(EventId(2))?.unwrap().name, "Dinner");
+    ///
+    /// db.change_event_id(EventId(1), EventId(3))?;
+    /// assert!(!db.contains_event(EventId(1)));
+    /// assert_eq!(db.fetch_event(EventId(3))?.unwrap().name, "Lunch");
+    /// assert_eq!(db.upcoming(Timestamp(0), 1)?[0].id, EventId(3));
+    /// let alice = db.fetch_user(UserId(1))?.unwrap();
+    /// assert_eq!(alice.events.into_iter().collect::<Vec<_>>(), [EventId(3)]);
+    /// # Ok(())
     /// # }
     /// ```
-    pub fn add_event(&self, event: Event) {
-        self.add_event_to_cache(&event);
-        let buf = self.open_buf_writer(event.id.0, "events").unwrap();
-        ron::ser::to_writer(buf, &event).unwrap();
-        if !event.users.is_empty() {
-            let mut users = vec![];
-            for u in event.users.iter() {
-                let user = self.fetch_user(*u);
-                users.push(user);
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(old = %old, new = %new), err)
+    )]
+    pub fn change_event_id(&self, old: EventId, new: EventId) -> Result<(), NariError> {
+        let mut event: Event = self
+            .read_entry(old.0, "events")?
+            .ok_or_else(|| NariError::NotFound(format!("event {old}")))?;
+        if old == new {
+            return Ok(());
+        }
+        if self.contains_event(new) {
+            return Err(NariError::AlreadyExists(format!("event {new}")));
+        }
+        event.id = new;
+        // Written before the old one is removed, so the event is never lost
+        self.write_entry(new.0, "events", &event)?;
+        self.update_cache(|tree| {
+            tree.remove_id(old.0);
+            if let Some(at) = event.queued_at() {
+                tree.insert(at, new.0);
+            }
+        })?;
+        for u in event.users.iter() {
+            if let Some(mut user) = self.fetch_user(*u)? {
+                user.events.remove(&old);
+                user.events.insert(new);
+                self.write_entry(user.id.0, "users", &user)?;
             }
-            self.add_event_to_users(event, users)
         }
+        self.remove_entry::<Event>(old.0, "events")?;
+        Ok(())
     }
-    /// Search for a event in the database, returns the event if found.
+    /// Adds a copy of the event `src` with the id `new_id` and returns it, see
+    /// [`Event::clone_with_id`]. The copy is in the same users as `src`, or in none
+    /// if `clear_users` is set.
+    ///
+    /// Returns [`NariError::NotFound`] if there is no event with the id `src`, and
+    /// [`NariError::AlreadyExists`] without changing anything if there is already an
+    /// event with the id `new_id`.
     ///
     /// ## Usage
-    /// ```no_run
-    /// # use nari::models::Database;
-    /// # use nari::models::EventId;
-    /// # use nari::models::event::EventBuilder;
-    /// # fn main() {
-    /// # let db = Database::new("./db/").unwrap();
-    /// let alices_birthday = EventBuilder::new(EventId(10), "Alice's Birthday", 123456789)
-    ///         .build();
-    /// db.add_event(alices_birthday.clone());
-    /// assert_eq!(alices_birthday, db.fetch_event(EventId(10)));
+    /// ```
+    /// # use nari::models::{Database, EventId, UserId};
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_duplicate_event");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::new(&dir)?;
+    /// db.create_user(UserId(1), "Alice")?;
+    /// db.build_event(EventId(1), "Standup", 1000).users([UserId(1)]).build().save_to_db(&db)?;
+    ///
+    /// let copy = db.duplicate_event(EventId(1), EventId(2), false)?;
+    /// assert_eq!(copy.name, "Standup");
+    /// assert_eq!(db.fetch_user(UserId(1))?.unwrap().events.len(), 2);
+    ///
+    /// let empty = db.duplicate_event(EventId(1), EventId(3), true)?;
+    /// assert!(empty.users.is_empty());
+    /// assert_eq!(db.fetch_user(UserId(1))?.unwrap().events.len(), 2);
+    ///
+    /// // Each one is saved on its own
+    /// let mut copy = db.fetch_event(EventId(2))?.unwrap();
+    /// copy.name = String::from("Retro");
+    /// db.update_event(copy)?;
+    /// assert_eq!(db.fetch_event(EventId(1))?.unwrap().name, "Standup");
+    /// assert_eq!(db.fetch_event(EventId(2))?.unwrap().name, "Retro");
+    /// # std::fs::remove_dir_all(&dir)?;
+    /// # Ok(())
     /// # }
     /// ```
-    pub fn fetch_event(&self, id: EventId) -> Event {
-        let buf = self.open_buf_reader(id.0, "events").unwrap();
-        ron::de::from_reader(buf).unwrap()
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(src = %src, new_id = %new_id, clear_users), err)
+    )]
+    pub fn duplicate_event(
+        &self,
+        src: EventId,
+        new_id: EventId,
+        clear_users: bool,
+    ) -> Result<Event, NariError> {
+        let event: Event = self
+            .read_entry(src.0, "events")?
+            .ok_or_else(|| NariError::NotFound(format!("event {src}")))?;
+        let mut copy = event.clone_with_id(new_id);
+        if clear_users {
+            copy.users.clear();
+        }
+        self.add_event_checked(copy.clone())?;
+        Ok(copy)
     }
 
     /// Takes an event and adds it to any amount of users, it can take any
@@ -182,7 +2734,10 @@ impl Database {
     /// [`User`] tracks what events it is in with a [`HashSet`](std::collections::HashSet) internally. This means
     /// that if any two or more events have the same [`EventId`], they won't repeat
     /// and only the latest one created will be used.
-    ///   
+    ///
+    /// Fails without saving anything if a user would be in more events than allowed,
+    /// see [`with_max_events_per_user`](Database::with_max_events_per_user).
+    ///
     /// ## Usage
     /// ```no_run
     /// # use nari::models::Database;
@@ -190,31 +2745,64 @@ impl Database {
     /// # use nari::models::{EventId, UserId};
     /// # use nari::models::User;
     /// # use nari::models::event::EventBuilder;
-    /// # fn main() {
-    /// # let db = Database::new("./db/").unwrap();
+    /// # fn main() -> std::io::Result<()> {
+    /// # let db = Database::new("./db/")?;
     /// # let alice = User::new(UserId(42), "Alice");
     /// # let bob = User::new(UserId(43), "Bob");
     /// let alices_birthday = EventBuilder::new(EventId(42), "Alice's Birthday", 123456789)
     ///         .description("Today is Alice's birthday! 🎉")
     ///         .build();
-    /// db.add_event_to_users(alices_birthday, [alice, bob]);
+    /// db.add_event_to_users(alices_birthday, [alice, bob])?;
+    /// # Ok(())
     /// # }
     /// ```
-    pub fn add_event_to_users<I>(&self, mut event: Event, users: I)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(id = %event.id), err))]
+    pub fn add_event_to_users<I>(&self, mut event: Event, users: I) -> Result<(), NariError>
     where
         I: IntoIterator<Item = User>,
     {
-        self.add_event_to_cache(&event);
-
-        for mut u in users {
+        let mut users: Vec<User> = users.into_iter().collect();
+        self.attach_event(&mut event, &mut users)
+    }
+    /// Adds the users to the event and the event to the users, then saves all of them,
+    /// writing each file once.
+    ///
+    /// Unlike [`add_event`](Database::add_event), which reads the users of the event
+    /// from the database, this works with the users as they are handed in, so use it
+    /// when they are already at hand. The objects are updated in place, ready to keep
+    /// using them afterwards.
+    ///
+    /// Fails without saving anything if a user would be in more events than allowed,
+    /// see [`with_max_events_per_user`](Database::with_max_events_per_user).
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::{Database, EventId, UserId};
+    /// # fn main() -> std::io::Result<()> {
+    /// # let db = Database::new("./db/")?;
+    /// let mut users = [db.create_user(UserId(42), "Alice")?, db.create_user(UserId(43), "Bob")?];
+    /// let mut party = db.build_event(EventId(42), "Party", 123456789).build();
+    /// db.attach_event(&mut party, &mut users)?;
+    ///
+    /// assert!(party.users.contains(&UserId(43)));
+    /// assert!(users[0].events.contains(&EventId(42)));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(id = %event.id), err))]
+    pub fn attach_event(&self, event: &mut Event, users: &mut [User]) -> Result<(), NariError> {
+        for u in users.iter() {
+            let joining = usize::from(!u.events.contains(&event.id));
+            self.check_event_capacity(u, joining)?;
+        }
+        for u in users.iter_mut() {
             u.events.insert(event.id);
-            let buf = self.open_buf_writer(u.id.0, "users").unwrap();
-            ron::ser::to_writer(buf, &u).unwrap();
-
+            self.write_entry(u.id.0, "users", u)?;
             event.users.insert(u.id);
         }
-        let buf = self.open_buf_writer(event.id.0, "events").unwrap();
-        ron::ser::to_writer(buf, &event).unwrap()
+        self.write_entry(event.id.0, "events", event)?;
+        // Queued last, as listeners drop the ids whose files they can't find
+        self.add_event_to_cache(event)
     }
     /// Takes an user and adds it to any amount of events, it can take any
     /// collection of [`Event`] as long as it implements the [`IntoIterator`] trait.
@@ -225,6 +2813,9 @@ impl Database {
     /// that if any two or more users have the same [`UserId`], they won't repeat
     /// and only the lastest one created will be used.
     ///
+    /// Fails without saving anything if the user would be in more events than allowed,
+    /// see [`with_max_events_per_user`](Database::with_max_events_per_user).
+    ///
     /// ## Usage
     /// ```no_run
     /// # use nari::models::Database;
@@ -233,8 +2824,8 @@ impl Database {
     /// # use nari::models::event::EventBuilder;
     /// # use nari::models::EventId;
     /// # use nari::models::User;
-    /// # fn main() {
-    /// # let db = Database::new("./db/").unwrap();
+    /// # fn main() -> std::io::Result<()> {
+    /// # let db = Database::new("./db/")?;
     /// let bob = User::new(UserId(43), "Bob");
     /// let alices_birthday = EventBuilder::new(EventId(42), "Alice's Birthday", 123456789)
     ///        .build();
@@ -242,79 +2833,979 @@ impl Database {
     ///       .build();  
     /// let park_hangout = EventBuilder::new(EventId(44), "Park hangout", 123456789)
     ///       .build();   
-    /// db.add_user_to_events(bob, [alices_birthday, job_meeting, park_hangout])
+    /// db.add_user_to_events(bob, [alices_birthday, job_meeting, park_hangout])?;
+    /// # Ok(())
     /// # }
     /// ```
-    pub fn add_user_to_events<I>(&self, mut user: User, events: I)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(id = %user.id), err))]
+    pub fn add_user_to_events<I>(&self, mut user: User, events: I) -> Result<(), NariError>
     where
         I: IntoIterator<Item = Event>,
     {
+        let events: Vec<Event> = events.into_iter().collect();
+        let joining = events
+            .iter()
+            .map(|e| e.id)
+            .filter(|id| !user.events.contains(id))
+            .collect::<HashSet<_>>()
+            .len();
+        self.check_event_capacity(&user, joining)?;
         for mut e in events {
-            self.add_event_to_cache(&e);
             e.users.insert(user.id);
-            let buf = self.open_buf_writer(e.id.0, "events").unwrap();
-            ron::ser::to_writer(buf, &e).unwrap();
+            self.write_entry(e.id.0, "events", &e)?;
+            self.add_event_to_cache(&e)?;
 
             user.events.insert(e.id);
         }
-        let buf = self.open_buf_writer(user.id.0, "users").unwrap();
-        ron::ser::to_writer(buf, &user).unwrap();
+        self.write_entry(user.id.0, "users", &user)
+    }
+    /// Takes an user out of an event, updating both the stored user and event so that
+    /// neither references the other anymore.
+    ///
+    /// Returns [`NariError::NotFound`] if the user doesn't exist, while a missing event
+    /// is only removed from the user.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::Database;
+    /// # use nari::models::{EventId, UserId};
+    /// # fn main() -> std::io::Result<()> {
+    /// # let db = Database::new("./db/")?;
+    /// let alice = db.create_user(UserId(42), "Alice")?;
+    /// let party = db.build_event(EventId(42), "Party", 123456789).build();
+    /// db.add_event_to_users(party, [alice])?;
+    ///
+    /// db.remove_event_from_user(UserId(42), EventId(42))?;
+    /// assert!(db.fetch_user(UserId(42))?.unwrap().events.is_empty());
+    /// assert!(db.fetch_event(EventId(42))?.unwrap().users.is_empty());
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(user = %user, event = %event), err))]
+    pub fn remove_event_from_user(&self, user: UserId, event: EventId) -> Result<(), NariError> {
+        let mut stored_user = self
+            .fetch_user(user)?
+            .ok_or_else(|| NariError::NotFound(format!("user {user}")))?;
+        if let Some(mut stored_event) = self.fetch_event(event)? {
+            if stored_event.users.remove(&user) {
+                self.write_entry(event.0, "events", &stored_event)?;
+            }
+        }
+        if stored_user.remove_event(event) {
+            self.write_entry(user.0, "users", &stored_user)?;
+        }
+        Ok(())
     }
     /// Reads the whole database and replaces the current event queue of future events
-    /// with the one read. It should fix any possible desync problems that may have arisen.
-    pub fn rewrite_cache(&self) {
-        let dir = std::fs::read_dir(self.base_path.join("events")).unwrap();
-        let mut events = BTreeMap::new();
-        for entry in dir {
-            let path = entry.unwrap().path();
-            if path.is_file() {
-                let file = File::open(path).unwrap();
-                let buf = BufReader::new(file);
-                let ev: Event = ron::de::from_reader(buf).unwrap();
-                events.insert(ev.next_occurence, ev.id.0);
-            }
-        }
-        let buf = BufWriter::new(File::open(self.base_path.join("event_cache.bin")).unwrap());
-        ron::ser::to_writer(buf, &events).unwrap();
+    /// with the one read. It should fix any possible desync problems that may have arisen,
+    /// even if the event queue itself got corrupted.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{Database, EventId, Timestamp};
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_rewrite_cache");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::new(&dir)?;
+    /// db.build_event(EventId(14), "Alice's Birthday", 123456789).build().save_to_db(&db)?;
+    /// std::fs::write(db.cache_path(), "not ron at all")?;
+    /// assert!(db.events_between(Timestamp(0), Timestamp(u64::MAX)).is_err());
+    ///
+    /// db.rewrite_cache()?;
+    /// let events = db.events_between(Timestamp(0), Timestamp(u64::MAX))?;
+    /// assert_eq!(events[0].id, EventId(14));
+    /// # std::fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, err))]
+    pub fn rewrite_cache(&self) -> Result<(), NariError> {
+        let mut tree = EventQueue::new();
+        for event in self.list_events()? {
+            let event = event?;
+            if let Some(at) = event.queued_at() {
+                tree.insert(at, event.id.0);
+            }
+        }
+        self.write_cache(&tree)
+    }
+    /// Cross-checks the event queue against the event files and returns where they
+    /// disagree, without changing anything.
+    ///
+    /// They drift apart when a write is interrupted or the files are edited by hand,
+    /// [`repair`](Database::repair) brings them back in line. It fails if the event
+    /// queue itself can't be read, which [`rewrite_cache`](Database::rewrite_cache)
+    /// fixes.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{Database, EventId, Timestamp};
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_verify");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::new(&dir)?;
+    /// for id in [1, 2] {
+    ///     db.build_event(EventId(id), "Reminder", 1000).build().save_to_db(&db)?;
+    /// }
+    /// assert!(db.verify()?.is_clean());
+    ///
+    /// // Changes the event files behind the back of the event queue
+    /// let other = Database::with_cache_name(&dir, "other_cache")?;
+    /// other.reschedule_event(EventId(1), Timestamp(2000))?;
+    /// other.delete_event(EventId(2))?;
+    /// other.build_event(EventId(3), "Reminder", 3000).build().save_to_db(&other)?;
+    ///
+    /// let report = db.verify()?;
+    /// assert_eq!(report.mismatched.len(), 1);
+    /// assert_eq!(report.mismatched[0].id, EventId(1));
+    /// assert_eq!(report.mismatched[0].queued_at, 1000);
+    /// assert_eq!(report.mismatched[0].expected, Some(2000));
+    /// assert_eq!(report.missing_files, [EventId(2)]);
+    /// assert_eq!(report.unqueued, [EventId(3)]);
+    ///
+    /// assert_eq!(db.repair()?, report);
+    /// assert!(db.verify()?.is_clean());
+    /// # std::fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, err)
+    )]
+    pub fn verify(&self) -> Result<VerifyReport, NariError> {
+        let mut queued: HashMap<u64, Vec<u64>> = HashMap::new();
+        for (at, id) in self.read_cache()?.range(..) {
+            queued.entry(id).or_default().push(at);
+        }
+        let mut report = VerifyReport::default();
+        for event in self.list_events()? {
+            let event = event?;
+            let expected = event.queued_at();
+            match queued.remove(&event.id.0) {
+                Some(times) => report.mismatched.extend(
+                    times
+                        .into_iter()
+                        .filter(|at| Some(*at) != expected)
+                        .map(|queued_at| CacheMismatch {
+                            id: event.id,
+                            queued_at,
+                            expected,
+                        }),
+                ),
+                None if expected.is_some() => report.unqueued.push(event.id),
+                None => (),
+            }
+        }
+        report.missing_files = queued.into_keys().map(EventId).collect();
+        report.mismatched.sort_by_key(|m| (m.id.0, m.queued_at));
+        report.missing_files.sort_by_key(|id| id.0);
+        report.unqueued.sort_by_key(|id| id.0);
+        Ok(report)
+    }
+    /// Checks the event queue like [`verify`](Database::verify) and, if it disagrees
+    /// with the event files, rebuilds it from them like
+    /// [`rewrite_cache`](Database::rewrite_cache). Returns what was fixed.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::Database;
+    /// # fn main() -> std::io::Result<()> {
+    /// # let db = Database::new("./db/")?;
+    /// let report = db.repair()?;
+    /// if !report.is_clean() {
+    ///     println!("Requeued {} events", report.unqueued.len());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, err))]
+    pub fn repair(&self) -> Result<VerifyReport, NariError> {
+        let report = self.verify()?;
+        if !report.is_clean() {
+            self.rewrite_cache()?;
+        }
+        Ok(report)
     }
+    /// Writes every user and event in the database to `writer` as a single JSON document,
+    /// only available with the `json` feature.
+    ///
+    /// The document is an object with a `users` and an `events` list, each sorted by id,
+    /// and can be read back with [`import_json`](Database::import_json).
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{Database, EventId, Timestamp, UserId};
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_export_json");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::new(dir.join("db"))?;
+    /// let alice = db.create_user(UserId(42), "Alice")?;
+    /// db.build_event(EventId(14), "Alice's Birthday", 123456789)
+    ///     .users([alice.id])
+    ///     .build()
+    ///     .save_to_db(&db)?;
+    /// let mut backup = vec![];
+    /// db.export_json(&mut backup)?;
+    ///
+    /// let copy = Database::new(dir.join("copy"))?;
+    /// copy.import_json(&backup[..])?;
+    /// let alice = db.fetch_user(UserId(42))?.unwrap();
+    /// assert_eq!(copy.fetch_user(UserId(42))?.unwrap().events, alice.events);
+    /// assert_eq!(copy.fetch_event(EventId(14))?, db.fetch_event(EventId(14))?);
+    /// let queued = copy.events_between(Timestamp(123456789), Timestamp(123456790))?;
+    /// assert_eq!(queued[0].id, EventId(14));
+    /// # std::fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, err))]
+    #[cfg(feature = "json")]
+    pub fn export_json<W>(&self, writer: W) -> Result<(), NariError>
+    where
+        W: io::Write,
+    {
+        let mut users = self.list_users()?;
+        users.sort_by_key(|u| u.id.0);
+        let mut events = self.list_events()?.collect::<Result<Vec<_>, NariError>>()?;
+        events.sort_by_key(|e| e.id.0);
 
-    fn open_buf_reader(&self, id: u64, folder: &str) -> io::Result<BufReader<File>> {
-        let path: PathBuf = [
-            &self.base_path,
-            &PathBuf::from(folder),
-            &PathBuf::from(format!("{id}.ron")),
-        ]
-        .iter()
-        .collect();
+        let export = JsonExport { users, events };
+        serde_json::to_writer(writer, &export)?;
+        Ok(())
+    }
+    /// Adds every user and event from a JSON document made by
+    /// [`export_json`](Database::export_json) to the database, only available with the
+    /// `json` feature.
+    ///
+    /// Users and events with the same id as the ones in the document are replaced,
+    /// and the event queue is rebuilt afterwards with [`rewrite_cache`](Database::rewrite_cache).
+    /// Events are added to the users they list. Fails with [`NariError::TooManyEvents`]
+    /// without importing anything if a user would join more events than
+    /// [`with_max_events_per_user`](Database::with_max_events_per_user) allows.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{Database, EventId, UserId};
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_import_json");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let phone = Database::new(dir.join("phone"))?;
+    /// phone.create_user(UserId(1), "Bob")?;
+    /// let lunch = phone.build_event(EventId(5), "Lunch with Carol", 1000).users([UserId(2)]);
+    /// lunch.build().save_to_db(&phone)?;
+    /// let mut backup = vec![];
+    /// phone.export_json(&mut backup)?;
+    ///
+    /// let laptop = Database::new(dir.join("laptop"))?;
+    /// laptop.create_user(UserId(1), "Robert")?;
+    /// laptop.create_user(UserId(2), "Carol")?;
+    /// laptop.import_json(&backup[..])?;
+    /// assert_eq!(laptop.fetch_user(UserId(1))?.unwrap().name, "Bob");
+    /// assert!(laptop.fetch_user(UserId(2))?.unwrap().events.contains(&EventId(5)));
+    /// # std::fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, err))]
+    #[cfg(feature = "json")]
+    pub fn import_json<R>(&self, reader: R) -> Result<(), NariError>
+    where
+        R: Read,
+    {
+        let import: JsonExport = serde_json::from_reader(reader)?;
+        let mut events = import.events;
+        for event in events.iter_mut() {
+            upgrade(event)?;
+        }
+        let mut joining = events_by_user(&events);
+        let mut users = vec![];
+        for mut user in import.users {
+            upgrade(&mut user)?;
+            // Only the events the stored user wasn't in yet count against the cap
+            let stored = self
+                .fetch_user(user.id)?
+                .map(|u| u.events)
+                .unwrap_or_default();
+            let (kept, new): (HashSet<EventId>, HashSet<EventId>) =
+                user.events.drain().partition(|e| stored.contains(e));
+            user.events = kept;
+            joining.entry(user.id).or_default().extend(new);
+            users.push(user);
+        }
+        let users = self.join_events(joining, users)?;
+        for user in users {
+            self.write_entry(user.id.0, "users", &user)?;
+        }
+        for event in events {
+            self.write_entry(event.id.0, "events", &event)?;
+        }
+        self.rewrite_cache()
+    }
+    /// Writes every event in the database to `writer` as an iCalendar (`.ics`) file that
+    /// can be imported by most calendar apps, only available with the `ical` feature.
+    ///
+    /// Each event becomes a VEVENT starting at its next occurence in UTC, or on its UTC
+    /// date for [`all_day`](Event::all_day) events, and repeating events get the
+    /// matching recurrence rule.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::Database;
+    /// # fn main() -> std::io::Result<()> {
+    /// # let db = Database::new("./db/")?;
+    /// let file = std::fs::File::create("./agenda.ics")?;
+    /// db.export_ics(file)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, err))]
+    #[cfg(feature = "ical")]
+    pub fn export_ics<W>(&self, writer: W) -> Result<(), NariError>
+    where
+        W: io::Write,
+    {
+        let mut events = self.list_events()?.collect::<Result<Vec<_>, NariError>>()?;
+        events.sort_by_key(|e| e.id.0);
+        Ok(ical::write_calendar(BufWriter::new(writer), &events)?)
+    }
 
-        Ok(BufReader::new(File::open(path)?))
+    // Drops the entry from the cache of fetched events, if it is an event there
+    #[cfg_attr(not(feature = "cache"), allow(unused_variables))]
+    pub(super) fn forget_cached(&self, id: u64, folder: &str) {
+        #[cfg(feature = "cache")]
+        if let (Some(hot), "events") = (&self.hot_events, folder) {
+            hot.lock().unwrap_or_else(|e| e.into_inner()).remove(id);
+        }
+    }
+    /// Returns the event `id` from the cache of fetched events, if its file was last
+    /// modified at `modified` like when it was cached.
+    #[cfg(feature = "cache")]
+    pub(super) fn cached_event(&self, id: u64, modified: SystemTime) -> Option<Event> {
+        let hot = self.hot_events.as_ref()?;
+        hot.lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(id, modified)
+    }
+    #[cfg(feature = "cache")]
+    pub(super) fn cache_event(&self, modified: SystemTime, event: &Event) {
+        if let Some(hot) = &self.hot_events {
+            let mut hot = hot.lock().unwrap_or_else(|e| e.into_inner());
+            hot.insert(event.id.0, modified, event.clone());
+        }
+    }
+    #[cfg(feature = "cache")]
+    pub(super) fn caches_events(&self) -> bool {
+        self.hot_events.is_some()
+    }
+    // The file holding the entry, shared with every other entry when consolidated
+    #[cfg(feature = "cache")]
+    pub(super) fn entry_file(&self, id: u64, folder: &str) -> PathBuf {
+        match self.layout {
+            Layout::Files => self.entry_path(id, folder),
+            Layout::Consolidated => self.consolidated_path(folder),
+        }
+    }
+    pub(super) fn entry_path(&self, id: u64, folder: &str) -> PathBuf {
+        let shards = if folder == "events" { self.shards } else { 0 };
+        entry_path(&self.base_path, folder, id, self.format, shards)
+    }
+    // The folders holding the entries, relative to the base path
+    fn entry_dirs(&self, folder: &str) -> Vec<PathBuf> {
+        match self.shards {
+            shards if shards > 1 && folder == "events" => (0..shards)
+                .map(|shard| Path::new(folder).join(shard.to_string()))
+                .collect(),
+            _ => vec![PathBuf::from(folder)],
+        }
+    }
+    fn consolidated_path(&self, folder: &str) -> PathBuf {
+        consolidated_path(&self.base_path, folder, self.format)
+    }
+    fn entry_exists<T>(&self, id: u64, folder: &str) -> bool
+    where
+        T: DeserializeOwned,
+    {
+        match self.layout {
+            Layout::Files => self.entry_path(id, folder).is_file(),
+            Layout::Consolidated => {
+                let path = self.consolidated_path(folder);
+                read_consolidated::<T>(&self.base_path, &path, self.format, self.codec)
+                    .is_ok_and(|entries| entries.contains_key(&id))
+            }
+        }
+    }
+    fn read_entry<T>(&self, id: u64, folder: &str) -> Result<Option<T>, NariError>
+    where
+        T: DeserializeOwned + Versioned,
+    {
+        if self.layout == Layout::Consolidated {
+            let path = self.consolidated_path(folder);
+            let mut entries = read_versioned_all(&self.base_path, &path, self.format, self.codec)?;
+            return Ok(entries.remove(&id));
+        }
+        let path = self.entry_path(id, folder);
+        match read_versioned(&self.base_path, &path, self.format, self.codec) {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if e.is_not_found() => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+    #[allow(clippy::type_complexity)]
+    fn list_entries<T>(
+        &self,
+        folder: &str,
+    ) -> Result<Box<dyn Iterator<Item = Result<T, NariError>>>, NariError>
+    where
+        T: DeserializeOwned + Versioned + 'static,
+    {
+        if self.layout == Layout::Consolidated {
+            let path = self.consolidated_path(folder);
+            let entries = read_versioned_all(&self.base_path, &path, self.format, self.codec)?;
+            return Ok(Box::new(entries.into_values().map(Ok)));
+        }
+        let mut entries = vec![];
+        for dir in self.entry_dirs(folder) {
+            entries.extend(fs::read_dir(self.base_path.join(dir))?);
+        }
+        let (base_path, format, codec) = (self.base_path.clone(), self.format, self.codec);
+        Ok(Box::new(entries.into_iter().filter_map(move |entry| {
+            let path = match entry {
+                Ok(entry) => entry.path(),
+                Err(e) => return Some(Err(e.into())),
+            };
+            if is_entry(&path, format) {
+                Some(read_versioned(&base_path, &path, format, codec))
+            } else {
+                None
+            }
+        })))
+    }
+    // Every lookup without an index of its own goes through here, scanning all the
+    // event files
+    fn filter_events<F>(&self, mut f: F) -> Result<Vec<Event>, NariError>
+    where
+        F: FnMut(&Event) -> bool,
+    {
+        let mut events = vec![];
+        for event in self.list_events()? {
+            let event = event?;
+            if f(&event) {
+                events.push(event);
+            }
+        }
+        Ok(events)
+    }
+    fn count_entries<T>(&self, folder: &str) -> Result<usize, NariError>
+    where
+        T: DeserializeOwned,
+    {
+        if self.layout == Layout::Consolidated {
+            let path = self.consolidated_path(folder);
+            return Ok(
+                read_consolidated::<T>(&self.base_path, &path, self.format, self.codec)?.len(),
+            );
+        }
+        let mut count = 0;
+        for dir in self.entry_dirs(folder) {
+            for entry in fs::read_dir(self.base_path.join(dir))? {
+                if is_entry(&entry?.path(), self.format) {
+                    count += 1;
+                }
+            }
+        }
+        Ok(count)
+    }
+    fn write_entry<T>(&self, id: u64, folder: &str, value: &T) -> Result<(), NariError>
+    where
+        T: Serialize + DeserializeOwned + Clone,
+    {
+        self.forget_cached(id, folder);
+        if self.layout == Layout::Consolidated {
+            return self.update_consolidated(folder, |entries: &mut BTreeMap<u64, T>| {
+                entries.insert(id, value.clone());
+            });
+        }
+        let path = self.entry_path(id, folder);
+        let file = file_in(&self.base_path, &path);
+        let bytes = encode_pretty(value, self.format, self.codec, self.pretty.as_ref(), file)?;
+        write_bytes_atomically(&path, &bytes)
+    }
+    fn remove_entry<T>(&self, id: u64, folder: &str) -> Result<(), NariError>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        self.forget_cached(id, folder);
+        match self.layout {
+            Layout::Files => Ok(fs::remove_file(self.entry_path(id, folder))?),
+            Layout::Consolidated => {
+                self.update_consolidated(folder, |entries: &mut BTreeMap<u64, T>| {
+                    entries.remove(&id);
+                })
+            }
+        }
+    }
+    fn update_consolidated<T, F>(&self, folder: &str, f: F) -> Result<(), NariError>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce(&mut BTreeMap<u64, T>),
+    {
+        update_consolidated(
+            &self.base_path,
+            &self.consolidated_path(folder),
+            self.lock_mode,
+            self.format,
+            self.codec,
+            self.pretty.as_ref(),
+            f,
+        )
+    }
+    pub(super) fn insert_event(&self, mut event: Event) -> Result<(), NariError> {
+        let mut users = vec![];
+        for u in event.users.iter() {
+            if let Some(user) = self.fetch_user(*u)? {
+                users.push(user);
+            }
+        }
+        self.attach_event(&mut event, &mut users)
+    }
+    // Adds the events in `joining` to their users, taken from `pending` or else from
+    // the database, and returns every user to write. Every user is checked against
+    // the cap before returning, so nothing has to be written if one goes over it
+    fn join_events(
+        &self,
+        joining: HashMap<UserId, HashSet<EventId>>,
+        pending: Vec<User>,
+    ) -> Result<Vec<User>, NariError> {
+        let mut pending: HashMap<UserId, User> = pending.into_iter().map(|u| (u.id, u)).collect();
+        let mut users = vec![];
+        for (id, events) in joining {
+            let user = match pending.remove(&id) {
+                Some(user) => Some(user),
+                None => self.fetch_user(id)?,
+            };
+            if let Some(mut user) = user {
+                let new = events.iter().filter(|e| !user.events.contains(e)).count();
+                self.check_event_capacity(&user, new)?;
+                user.events.extend(events);
+                users.push(user);
+            }
+        }
+        users.extend(pending.into_values());
+        Ok(users)
+    }
+    // Fails if `user` can't join `joining` more events
+    pub(super) fn check_event_capacity(
+        &self,
+        user: &User,
+        joining: usize,
+    ) -> Result<(), NariError> {
+        match self.max_events_per_user {
+            Some(max) if joining > 0 && user.events.len() + joining > max => {
+                Err(NariError::TooManyEvents { user: user.id, max })
+            }
+            _ => Ok(()),
+        }
+    }
+    // Queues the event at its next occurence, dropping any previous entry for it
+    fn add_event_to_cache(&self, ev: &Event) -> Result<(), NariError> {
+        self.update_cache(|tree| {
+            tree.remove_id(ev.id.0);
+            if let Some(at) = ev.queued_at() {
+                tree.insert(at, ev.id.0);
+            }
+        })
     }
-    fn open_buf_writer(&self, id: u64, folder: &str) -> io::Result<BufWriter<File>> {
-        let path: PathBuf = [
+    // The cache is always replaced as a whole, so it can be read without locking
+    fn read_cache(&self) -> Result<EventQueue, NariError> {
+        read_path(&self.base_path, &self.cache_path(), self.format, self.codec)
+    }
+    fn update_cache<F>(&self, f: F) -> Result<(), NariError>
+    where
+        F: FnOnce(&mut EventQueue),
+    {
+        update_cache_at(
             &self.base_path,
-            &PathBuf::from(folder),
-            &PathBuf::from(format!("{id}.ron")),
-        ]
-        .iter()
-        .collect();
-
-        Ok(BufWriter::new(File::create(path)?))
+            &self.cache_path(),
+            self.lock_mode,
+            self.format,
+            self.codec,
+            f,
+        )
     }
-    fn add_event_to_cache(&self, ev: &Event) {
-        let options = FileOptions::new().read(true).write(true).create(true);
-        let mut filelock =
-            FileLock::lock(self.base_path.join("event_cache.ron"), true, options).unwrap();
+    fn write_cache(&self, tree: &EventQueue) -> Result<(), NariError> {
+        let _filelock = lock_cache(&self.cache_path(), self.lock_mode)?;
+        write_atomically(
+            &self.base_path,
+            &self.cache_path(),
+            tree,
+            self.format,
+            self.codec,
+        )
+    }
+    fn next_id<T, F>(&self, counter: F, folder: &str) -> Result<u64, NariError>
+    where
+        T: DeserializeOwned,
+        F: FnOnce(&mut IdCounters) -> &mut u64,
+    {
+        let mut lock = lock_file(
+            &self.base_path.join(ID_COUNTERS_FILE),
+            || FileOptions::new().read(true).write(true).create(true),
+            self.lock_mode,
+        )?;
+        let filelock = &mut lock.filelock;
 
         let mut bytes = vec![];
-        filelock.file.read_to_end(&mut bytes).unwrap();
-        let mut tree: BTreeMap<u64, u64> = ron::de::from_bytes(&bytes).unwrap();
-        tree.insert(ev.next_occurence, ev.id.0);
+        filelock.file.read_to_end(&mut bytes)?;
+        let mut counters: IdCounters = if bytes.is_empty() {
+            IdCounters::default()
+        } else {
+            let path = self.base_path.join(ID_COUNTERS_FILE);
+            ron::de::from_bytes(&bytes).map_err(|e| NariError::from(e).in_file(&path))?
+        };
+
+        let last = counter(&mut counters);
+        let mut id = last
+            .checked_add(1)
+            .ok_or_else(|| io::Error::other(format!("ran out of ids for {folder}")))?;
+        while self.entry_exists::<T>(id, folder) {
+            id += 1;
+        }
+        *last = id;
+
+        // Overwritten in place, as replacing the file would drop the lock held on it,
+        // and only cut to length afterwards so the counters are never left empty
+        let bytes = ron::ser::to_string(&counters)?.into_bytes();
+        filelock.file.seek(SeekFrom::Start(0))?;
+        filelock.file.write_all(&bytes)?;
+        filelock.file.set_len(bytes.len() as u64)?;
+        filelock.file.sync_data()?;
+        Ok(id)
+    }
+}
+
+/// Returns the events each user in `events` is in.
+fn events_by_user(events: &[Event]) -> HashMap<UserId, HashSet<EventId>> {
+    let mut joining: HashMap<UserId, HashSet<EventId>> = HashMap::new();
+    for event in events.iter() {
+        for u in event.users.iter() {
+            joining.entry(*u).or_default().insert(event.id);
+        }
+    }
+    joining
+}
+
+/// Returns the name the event queue is saved as by default, see [`Database::new`].
+pub(super) fn cache_file_name(format: SerdeFormat) -> String {
+    format!("event_cache.{}", format.extension())
+}
+// Compares every field, unlike `PartialEq` which only compares ids. Destructured so
+// that new fields can't be left out
+fn same_user(a: &User, b: &User) -> bool {
+    let User {
+        id,
+        name,
+        events,
+        version,
+    } = a;
+    *id == b.id && *name == b.name && *events == b.events && *version == b.version
+}
+fn same_event(a: &Event, b: &Event) -> bool {
+    let Event {
+        id,
+        name,
+        description,
+        next_occurence,
+        users,
+        repeats,
+        priority,
+        end,
+        timezone,
+        tags,
+        metadata,
+        max_occurrences,
+        occurrences_fired,
+        lead_secs,
+        enabled,
+        all_day,
+        depends_on,
+        day_of_month,
+        version,
+    } = a;
+    *id == b.id
+        && *name == b.name
+        && *description == b.description
+        && *next_occurence == b.next_occurence
+        && *users == b.users
+        && *repeats == b.repeats
+        && *priority == b.priority
+        && *end == b.end
+        && *timezone == b.timezone
+        && *tags == b.tags
+        && *metadata == b.metadata
+        && *max_occurrences == b.max_occurrences
+        && *occurrences_fired == b.occurrences_fired
+        && *lead_secs == b.lead_secs
+        && *enabled == b.enabled
+        && *all_day == b.all_day
+        && *depends_on == b.depends_on
+        && *day_of_month == b.day_of_month
+        && *version == b.version
+}
+/// Returns the path of the user or event `id` of a database at `base_path`, `folder`
+/// being either `users` or `events`, inside of its shard when there is more than one.
+pub(super) fn entry_path(
+    base_path: &Path,
+    folder: &str,
+    id: u64,
+    format: SerdeFormat,
+    shards: u64,
+) -> PathBuf {
+    let mut path = base_path.join(folder);
+    if shards > 1 {
+        path.push((id % shards).to_string());
+    }
+    path.push(format!("{id}.{}", format.extension()));
+    path
+}
+/// Returns the path of the file holding every user or every event of a consolidated
+/// database at `base_path`, `folder` being either `users` or `events`.
+pub(super) fn consolidated_path(base_path: &Path, folder: &str, format: SerdeFormat) -> PathBuf {
+    base_path.join(format!("{folder}.{}", format.extension()))
+}
+/// Reads every entry of the consolidated file at `path`, none if it wasn't written yet.
+pub(super) fn read_consolidated<T>(
+    base_path: &Path,
+    path: &Path,
+    format: SerdeFormat,
+    codec: Codec,
+) -> Result<BTreeMap<u64, T>, NariError>
+where
+    T: DeserializeOwned,
+{
+    match read_path(base_path, path, format, codec) {
+        Err(e) if e.is_not_found() => Ok(BTreeMap::new()),
+        result => result,
+    }
+}
+/// Locks the consolidated file at `path` and replaces its entries with the result of
+/// `f`.
+pub(super) fn update_consolidated<T, F>(
+    base_path: &Path,
+    path: &Path,
+    mode: LockMode,
+    format: SerdeFormat,
+    codec: Codec,
+    pretty: Option<&PrettyConfig>,
+    f: F,
+) -> Result<(), NariError>
+where
+    T: Serialize + DeserializeOwned,
+    F: FnOnce(&mut BTreeMap<u64, T>),
+{
+    let _filelock = lock_consolidated(path, mode)?;
+    let mut entries = read_consolidated(base_path, path, format, codec)?;
+    f(&mut entries);
+    let file = file_in(base_path, path);
+    write_bytes_atomically(path, &encode_pretty(&entries, format, codec, pretty, file)?)
+}
+/// Returns where `path` is inside of the database at `base_path`, which encrypted
+/// files are bound to, or the whole path if it is somewhere else.
+pub(super) fn file_in<'a>(base_path: &Path, path: &'a Path) -> &'a Path {
+    path.strip_prefix(base_path).unwrap_or(path)
+}
+/// Reads `path`, a file of the database at `base_path`, in `format`, decrypting it
+/// with the key of `codec` if it is encrypted.
+pub(super) fn read_path<T>(
+    base_path: &Path,
+    path: &Path,
+    format: SerdeFormat,
+    codec: Codec,
+) -> Result<T, NariError>
+where
+    T: DeserializeOwned,
+{
+    decode(&fs::read(path)?, format, codec, file_in(base_path, path)).map_err(|e| e.in_file(path))
+}
+/// Reads `path` like [`read_path`], also returning how it was encoded.
+pub(super) fn read_path_with_codec<T>(
+    base_path: &Path,
+    path: &Path,
+    format: SerdeFormat,
+    codec: Codec,
+) -> Result<(T, Codec), NariError>
+where
+    T: DeserializeOwned,
+{
+    let bytes = fs::read(path)?;
+    let value =
+        decode(&bytes, format, codec, file_in(base_path, path)).map_err(|e| e.in_file(path))?;
+    Ok((value, Codec::detect(&bytes)))
+}
+/// Whether `path` holds a user or an event, leaving out temporary files and any
+/// cache that may share their folder.
+fn is_entry(path: &Path, format: SerdeFormat) -> bool {
+    path.is_file()
+        && path.extension() == Some(format.extension().as_ref())
+        && path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .is_some_and(|stem| stem.parse::<u64>().is_ok())
+}
+/// Locks the cache at `cache_path` and replaces it with the result of `f`.
+pub(super) fn update_cache_at<F>(
+    base_path: &Path,
+    cache_path: &Path,
+    mode: LockMode,
+    format: SerdeFormat,
+    codec: Codec,
+    f: F,
+) -> Result<(), NariError>
+where
+    F: FnOnce(&mut EventQueue),
+{
+    let _filelock = lock_cache(cache_path, mode)?;
+    let mut tree: EventQueue = read_path(base_path, cache_path, format, codec)?;
+    f(&mut tree);
+    write_atomically(base_path, cache_path, &tree, format, codec)
+}
+/// Serializes `value` into a temporary file next to `path`, a file of the database at
+/// `base_path`, and then renames it over `path`, so that nobody can see it partially
+/// written.
+pub(super) fn write_atomically<T>(
+    base_path: &Path,
+    path: &Path,
+    value: &T,
+    format: SerdeFormat,
+    codec: Codec,
+) -> Result<(), NariError>
+where
+    T: Serialize,
+{
+    let file = file_in(base_path, path);
+    write_bytes_atomically(path, &encode(value, format, codec, file)?)
+}
+/// Writes `bytes` to `path` like [`write_atomically`], for values already encoded.
+fn write_bytes_atomically(path: &Path, bytes: &[u8]) -> Result<(), NariError> {
+    let tmp_path = tmp_path(path);
+    let mut file = File::create(&tmp_path)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    Ok(fs::rename(tmp_path, path)?)
+}
+/// Returns a new path to write `path` to before renaming it.
+pub(super) fn tmp_path(path: &Path) -> PathBuf {
+    // Every write gets its own temporary file, so concurrent writes of the same
+    // file can't rename each other's
+    let mut tmp_path = path.as_os_str().to_owned();
+    let n = TMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed);
+    tmp_path.push(format!(".{}.{n}.tmp", std::process::id()));
+    PathBuf::from(tmp_path)
+}
+/// Holds a file locked against other processes and other threads, see [`lock_file`].
+pub(super) struct HeldLock {
+    // Dropped in order, releasing the file before letting other threads in
+    pub(super) filelock: FileLock,
+    _guard: HeldPath,
+}
+/// Marks a file as locked by a thread of this process until dropped.
+struct HeldPath(PathBuf);
 
-        let options = FileOptions::new().truncate(true).write(true).create(true);
-        let filelock =
-            FileLock::lock(self.base_path.join("event_cache.ron"), true, options).unwrap();
+impl HeldPath {
+    /// Waits until no other thread holds the file at `path`.
+    fn acquire(path: PathBuf) -> Self {
+        let mut held = HELD_LOCKS.lock().unwrap_or_else(|e| e.into_inner());
+        while held.contains(&path) {
+            held = LOCK_RELEASED.wait(held).unwrap_or_else(|e| e.into_inner());
+        }
+        held.insert(path.clone());
+        HeldPath(path)
+    }
+    /// Returns `None` if another thread holds the file at `path`.
+    fn try_acquire(path: &Path) -> Option<Self> {
+        let mut held = HELD_LOCKS.lock().unwrap_or_else(|e| e.into_inner());
+        held.insert(path.to_path_buf())
+            .then(|| HeldPath(path.to_path_buf()))
+    }
+}
 
-        let writer = BufWriter::new(&filelock.file);
-        ron::ser::to_writer(writer, &tree).unwrap()
+impl Drop for HeldPath {
+    fn drop(&mut self) {
+        let mut held = HELD_LOCKS.lock().unwrap_or_else(|e| e.into_inner());
+        held.remove(&self.0);
+        LOCK_RELEASED.notify_all();
+    }
+}
+/// Locks the cache at `cache_path` for writing until the lock is dropped.
+///
+/// The lock is taken on a separate `.lock` file, as the cache itself is replaced
+/// on every write.
+pub(super) fn lock_cache(cache_path: &Path, mode: LockMode) -> Result<HeldLock, NariError> {
+    let mut lock_path = cache_path.as_os_str().to_owned();
+    lock_path.push(".lock");
+    lock_file(
+        Path::new(&lock_path),
+        || FileOptions::new().write(true).create(true),
+        mode,
+    )
+}
+/// Locks the consolidated file at `path` until the lock is dropped, on a separate
+/// `.lock` file like [`lock_cache`].
+fn lock_consolidated(path: &Path, mode: LockMode) -> Result<HeldLock, NariError> {
+    let mut lock_path = path.as_os_str().to_owned();
+    lock_path.push(".lock");
+    lock_file(
+        Path::new(&lock_path),
+        || FileOptions::new().write(true).create(true),
+        mode,
+    )
+}
+/// Locks the file at `path`, opened with `options`, until the lock is dropped.
+///
+/// File locks are held per process, so threads are kept in line separately, each
+/// file on its own so that other databases aren't held up.
+fn lock_file<F>(path: &Path, options: F, mode: LockMode) -> Result<HeldLock, NariError>
+where
+    F: Fn() -> FileOptions,
+{
+    // The same file may be reached through different paths
+    let held_path = match (path.parent().map(fs::canonicalize), path.file_name()) {
+        (Some(Ok(dir)), Some(name)) => dir.join(name),
+        _ => path.to_path_buf(),
+    };
+    let timeout = match mode {
+        LockMode::Blocking => {
+            let guard = HeldPath::acquire(held_path);
+            return Ok(HeldLock {
+                filelock: FileLock::lock(path, true, options())?,
+                _guard: guard,
+            });
+        }
+        LockMode::Retry { timeout } => timeout,
+    };
+    let started = Instant::now();
+    let mut pause = Duration::from_millis(1);
+    loop {
+        if let Some(guard) = HeldPath::try_acquire(&held_path) {
+            match FileLock::lock(path, false, options()) {
+                Ok(filelock) => {
+                    return Ok(HeldLock {
+                        filelock,
+                        _guard: guard,
+                    })
+                }
+                // Depending on the platform a taken lock is reported either way
+                Err(e)
+                    if e.kind() == io::ErrorKind::WouldBlock
+                        || e.kind() == io::ErrorKind::PermissionDenied => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        let elapsed = started.elapsed();
+        if elapsed >= timeout {
+            return Err(NariError::LockTimeout(path.to_path_buf()));
+        }
+        std::thread::sleep(pause.min(timeout - elapsed));
+        pause = (pause * 2).min(MAX_RETRY_PAUSE);
     }
 }
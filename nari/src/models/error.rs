@@ -0,0 +1,150 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use thiserror::Error;
+
+use super::{EventId, UserId};
+
+/// Everything that can go wrong when using nari.
+///
+/// It converts into an [`io::Error`] of the closest kind, so it can be used with `?`
+/// in functions that return [`io::Result`].
+///
+/// ## Usage
+/// ```no_run
+/// # use nari::models::{Database, EventId, NariError, Timestamp};
+/// # fn main() -> std::io::Result<()> {
+/// # let db = Database::new("./db/")?;
+/// match db.reschedule_event(EventId(7), Timestamp(123456789)) {
+///     Ok(()) => println!("Rescheduled"),
+///     Err(NariError::NotFound(what)) => println!("There is no {what}"),
+///     Err(NariError::InvalidFile { path, source }) => {
+///         println!("{} is corrupt: {source}", path.display())
+///     }
+///     Err(e) => return Err(e.into()),
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum NariError {
+    /// Reading or writing a file failed.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// A file couldn't be read or written as RON, most likely because it is corrupt.
+    #[error("invalid RON: {0}")]
+    Serialization(#[from] ron::Error),
+    /// A JSON document couldn't be read or written, only with the `json` feature.
+    #[cfg(feature = "json")]
+    #[error("invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    /// A file couldn't be read or written as bincode, only with the `bincode` feature.
+    #[cfg(feature = "bincode")]
+    #[error("invalid bincode: {0}")]
+    Bincode(#[from] bincode::Error),
+    /// The user or event, like `event 7`, doesn't exist.
+    #[error("{0} not found")]
+    NotFound(String),
+    /// The user or event, like `event 7`, already exists.
+    #[error("{0} already exists")]
+    AlreadyExists(String),
+    /// A lock wasn't freed in time, see [`LockMode`](super::LockMode).
+    #[error("timed out waiting for the lock on {}", .0.display())]
+    LockTimeout(PathBuf),
+    /// An event didn't fire in time, see
+    /// [`Database::wait_for_timeout`](super::Database::wait_for_timeout).
+    #[error("event {id} didn't fire in {timeout:?}")]
+    WaitTimeout { id: EventId, timeout: Duration },
+    /// The user would be in more events than allowed, see
+    /// [`Database::with_max_events_per_user`](super::Database::with_max_events_per_user).
+    #[error("user {user} can't be in more than {max} events")]
+    TooManyEvents { user: UserId, max: usize },
+    /// An argument isn't valid.
+    #[error("{0}")]
+    InvalidInput(String),
+    /// A file holds data that can't be used, like a newer version of the format.
+    #[error("{0}")]
+    InvalidData(String),
+    /// The file at `path` couldn't be read as a user, an event or any other part of
+    /// the database, `source` says why.
+    #[error("{}: {source}", path.display())]
+    InvalidFile {
+        path: PathBuf,
+        source: Box<NariError>,
+    },
+}
+impl NariError {
+    /// Whether the error comes from something that doesn't exist, either a user or
+    /// an event or one of the files of the database.
+    pub fn is_not_found(&self) -> bool {
+        match self {
+            Self::NotFound(_) => true,
+            Self::Io(e) => e.kind() == io::ErrorKind::NotFound,
+            _ => false,
+        }
+    }
+    /// Returns the path of the file the error comes from, if it comes from reading one
+    /// that is corrupt or otherwise unusable.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{Database, EventId};
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_error_path");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::new(&dir)?;
+    /// db.build_event(EventId(7), "Tea time", 1000).build().save_to_db(&db)?;
+    /// std::fs::write(dir.join("events").join("7.ron"), "not ron at all")?;
+    ///
+    /// let err = db.fetch_event(EventId(7)).err().unwrap();
+    /// assert!(err.to_string().contains("7.ron"));
+    /// assert_eq!(err.path(), Some(dir.join("events").join("7.ron").as_path()));
+    ///
+    /// let err = db.list_events()?.find_map(Result::err).unwrap();
+    /// assert!(err.to_string().contains("7.ron"));
+    /// # std::fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Self::InvalidFile { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+    /// Wraps the error in [`NariError::InvalidFile`] for the file at `path`.
+    pub(super) fn in_file(self, path: &Path) -> Self {
+        Self::InvalidFile {
+            path: path.to_path_buf(),
+            source: Box::new(self),
+        }
+    }
+    fn io_kind(&self) -> io::ErrorKind {
+        match self {
+            Self::Io(e) => e.kind(),
+            Self::NotFound(_) => io::ErrorKind::NotFound,
+            Self::AlreadyExists(_) => io::ErrorKind::AlreadyExists,
+            Self::LockTimeout(_) | Self::WaitTimeout { .. } => io::ErrorKind::TimedOut,
+            Self::InvalidInput(_) | Self::TooManyEvents { .. } => io::ErrorKind::InvalidInput,
+            Self::InvalidFile { source, .. } => source.io_kind(),
+            _ => io::ErrorKind::InvalidData,
+        }
+    }
+}
+impl From<ron::error::SpannedError> for NariError {
+    fn from(error: ron::error::SpannedError) -> Self {
+        Self::Serialization(error.code)
+    }
+}
+impl From<NariError> for io::Error {
+    fn from(error: NariError) -> Self {
+        if let NariError::Io(e) = error {
+            return e;
+        }
+        io::Error::new(error.io_kind(), error)
+    }
+}
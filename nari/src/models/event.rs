@@ -1,35 +1,360 @@
 use std::{
-    collections::{BTreeMap, HashSet},
-    fs::File,
-    io::{BufReader, Read},
-    path::Path,
-    sync::{Arc, Mutex},
-    time::{SystemTime, UNIX_EPOCH},
+    collections::{BTreeMap, BTreeSet, HashSet},
+    future::{self, Future},
+    io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{mpsc as std_mpsc, Arc, Mutex},
+    thread::JoinHandle,
+    time::SystemTime,
 };
 
-use file_lock::{FileLock, FileOptions};
-use notify::{event::ModifyKind::Data, EventKind, RecommendedWatcher, Watcher};
+use notify::{EventKind, RecommendedWatcher, Watcher};
 use serde::{Deserialize, Serialize};
 use tokio::{
-    sync::mpsc,
+    sync::{broadcast, mpsc},
     time::{interval, Duration},
 };
+use tokio_util::sync::CancellationToken;
 
-use super::{Database, EventId, UserId};
+use super::{
+    calendar::{add_months, civil_from_days, days_in_month, SECS_PER_DAY, SECS_PER_HOUR},
+    codec::{Codec, SerdeFormat},
+    database::{
+        cache_file_name, consolidated_path, entry_path, lock_cache, read_path,
+        read_path_with_codec, update_consolidated, write_atomically,
+    },
+    queue::EventQueue,
+    schema::{legacy_version, read_versioned, read_versioned_all, SCHEMA_VERSION},
+    Database, EventId, Layout, LockMode, NariError, Timestamp, UserId,
+};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Event {
     pub id: EventId,
     pub name: String,
     pub description: String,
-    pub next_occurence: u64,
+    pub next_occurence: Timestamp,
     pub users: HashSet<UserId>,
     pub repeats: Repeatability,
     pub priority: Priority,
+    /// Unix timestamp of when the event ends, events without one happen at an instant.
+    #[serde(default)]
+    pub end: Option<Timestamp>,
+    /// IANA name of the time zone the event is scheduled in, like `Europe/Berlin`.
+    ///
+    /// With the `chrono` feature, repeating events with a time zone keep their
+    /// wall-clock time in it instead of repeating every fixed amount of seconds.
+    #[serde(default)]
+    pub timezone: Option<String>,
+    /// Free-form labels to group events by, like `work` or `personal`.
+    #[serde(default)]
+    pub tags: HashSet<String>,
+    /// Data attached by the application, like a URL or an external id.
+    ///
+    /// It is opaque to nari, which only stores it and never uses it for scheduling.
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+    /// How many times a repeating event fires before it stops, `None` repeats forever.
+    #[serde(default)]
+    pub max_occurrences: Option<u32>,
+    /// How many times the event has fired so far.
+    #[serde(default)]
+    pub occurrences_fired: u32,
+    /// Seconds before its next occurence that a listener delivers the event as a
+    /// reminder, see [`FiredEvent::is_reminder`] and [`LeadPolicy`].
+    #[serde(default)]
+    pub lead_secs: Option<u64>,
+    /// Whether the event is queued to fire, paused events are kept but never fire,
+    /// see [`Database::set_event_enabled`].
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    /// Whether the event lasts the whole day, listeners fire all-day events at the
+    /// start of the day of their next occurence instead of at its exact second.
+    ///
+    /// The day starts at midnight UTC or, with the `chrono` feature and a known
+    /// [`timezone`](Event::timezone), at midnight in that time zone.
+    #[serde(default)]
+    pub all_day: bool,
+    /// Event that has to fire before this one can, see [`EventBuilder::depends_on`].
+    #[serde(default)]
+    pub depends_on: Option<EventId>,
+    /// Day of the month the event was scheduled on, which month based repetitions go
+    /// back to after a month too short for it, see [`Event::next_repetition`].
+    #[serde(default)]
+    pub day_of_month: Option<u32>,
+    /// Version of the format the event is saved in, see [`SCHEMA_VERSION`].
+    #[serde(default = "legacy_version")]
+    pub version: u32,
 }
 impl Event {
-    pub fn save_to_db(self, db: &Database) {
-        db.add_event(self);
+    /// Adds the event to `db`, see [`Database::add_event`].
+    pub fn save_to_db(self, db: &Database) -> Result<(), NariError> {
+        db.add_event(self)
+    }
+    /// Returns a copy of the event with the id `new_id`, which hasn't fired yet, to
+    /// use the event as a template, see [`Database::duplicate_event`].
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{event::EventBuilder, EventId, UserId};
+    /// let standup = EventBuilder::new(EventId(1), "Standup", 1000)
+    ///     .users([UserId(1)])
+    ///     .build();
+    /// let mut retro = standup.clone_with_id(EventId(2));
+    /// retro.name = String::from("Retro");
+    /// retro.users.clear();
+    /// assert_eq!(retro.id, EventId(2));
+    /// assert_eq!(retro.next_occurence, standup.next_occurence);
+    /// assert_eq!(standup.name, "Standup");
+    /// assert_eq!(standup.users.len(), 1);
+    /// ```
+    pub fn clone_with_id(&self, new_id: EventId) -> Event {
+        Event {
+            id: new_id,
+            occurrences_fired: 0,
+            ..self.clone()
+        }
+    }
+    /// Returns whether the unix timestamp `ts` is within the event, from its next
+    /// occurence (inclusive) to its end (exclusive).
+    ///
+    /// Events without an end are only active at the exact second they happen.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{event::EventBuilder, EventId};
+    /// let meeting = EventBuilder::new(EventId(1), "Meeting", 1000)
+    ///     .duration(60 * 60)
+    ///     .build();
+    /// assert!(meeting.is_active_at(1000));
+    /// assert!(meeting.is_active_at(4599));
+    /// assert!(!meeting.is_active_at(4600));
+    /// assert!(!meeting.is_active_at(999));
+    /// ```
+    pub fn is_active_at<T>(&self, ts: T) -> bool
+    where
+        T: Into<Timestamp>,
+    {
+        let ts = ts.into();
+        match self.end {
+            Some(end) => self.next_occurence <= ts && ts < end,
+            None => self.next_occurence == ts,
+        }
+    }
+    /// Returns whether the next occurence is before the unix timestamp `now`, an event
+    /// happening exactly at `now` isn't overdue yet.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{event::EventBuilder, EventId};
+    /// let meeting = EventBuilder::new(EventId(1), "Meeting", 1000).build();
+    /// assert!(!meeting.is_overdue(999));
+    /// assert!(!meeting.is_overdue(1000));
+    /// assert!(meeting.is_overdue(1001));
+    /// ```
+    pub fn is_overdue<T>(&self, now: T) -> bool
+    where
+        T: Into<Timestamp>,
+    {
+        self.next_occurence < now.into()
+    }
+    /// Returns the seconds from the unix timestamp `now` until the next occurence,
+    /// negative if it is in the past.
+    ///
+    /// Differences that don't fit in an `i64` are clamped to [`i64::MAX`] and
+    /// [`i64::MIN`].
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{event::EventBuilder, EventId};
+    /// let meeting = EventBuilder::new(EventId(1), "Meeting", 1000).build();
+    /// assert_eq!(meeting.time_until(400), 600);
+    /// assert_eq!(meeting.time_until(1000), 0);
+    /// assert_eq!(meeting.time_until(1600), -600);
+    ///
+    /// let far_future = EventBuilder::new(EventId(2), "Far future", u64::MAX).build();
+    /// assert_eq!(far_future.time_until(0), i64::MAX);
+    /// let far_past = EventBuilder::new(EventId(3), "Far past", 0).build();
+    /// assert_eq!(far_past.time_until(u64::MAX), i64::MIN);
+    /// assert_eq!(far_past.time_until(1 << 63), i64::MIN);
+    /// ```
+    pub fn time_until<T>(&self, now: T) -> i64
+    where
+        T: Into<Timestamp>,
+    {
+        let at = self.next_occurence.0;
+        let now = now.into().0;
+        if at >= now {
+            i64::try_from(at - now).unwrap_or(i64::MAX)
+        } else {
+            i64::try_from(now - at).map_or(i64::MIN, |secs| -secs)
+        }
+    }
+    /// Returns the occurence that comes after the unix timestamp `from`, following
+    /// [`Repeatability::advance`] or, with the `chrono` feature and a known
+    /// [`timezone`](Event::timezone), [`Repeatability::advance_in`].
+    ///
+    /// Month based repetitions go back to the [`day_of_month`](Event::day_of_month)
+    /// the event was scheduled on once the month is long enough for it, instead of
+    /// keeping the day they were clamped to.
+    pub fn next_repetition<T>(&self, from: T) -> Option<Timestamp>
+    where
+        T: Into<Timestamp>,
+    {
+        let from = from.into().0;
+        let day = self.repeat_day(from)?;
+        #[cfg(feature = "chrono")]
+        if let Some(tz) = self.timezone.as_deref().and_then(|tz| tz.parse().ok()) {
+            return self.repeats.advance_in_to_day(Timestamp(from), tz, day);
+        }
+        self.repeats.advance_to_day(Timestamp(from), day)
+    }
+    /// Returns how many more times the event fires, or `None` if it has no limit.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{event::{EventBuilder, Repeatability}, EventId};
+    /// let mut reminder = EventBuilder::new(EventId(1), "Water the plants", 1000)
+    ///     .repeats(Repeatability::Weekly)
+    ///     .max_occurrences(3)
+    ///     .build();
+    /// assert_eq!(reminder.remaining_occurrences(), Some(3));
+    ///
+    /// reminder.occurrences_fired = 3;
+    /// assert_eq!(reminder.remaining_occurrences(), Some(0));
+    /// ```
+    pub fn remaining_occurrences(&self) -> Option<u32> {
+        self.max_occurrences
+            .map(|max| max.saturating_sub(self.occurrences_fired))
+    }
+    /// Returns up to `count` occurences of the event from the unix timestamp `from` on,
+    /// starting at its next occurence and following
+    /// [`next_repetition`](Event::next_repetition).
+    ///
+    /// Events that don't repeat have at most their next occurence, and events with a
+    /// [`max_occurrences`](Event::max_occurrences) stop once they run out, counting
+    /// the occurences before `from` too. Month based repetitions that had to clamp
+    /// the day go back to the original day when they can.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{event::{EventBuilder, Repeatability}, EventId, Timestamp};
+    /// // 2024-02-29 00:00:00 UTC
+    /// let leap_day = EventBuilder::new(EventId(1), "Leap day", 1709164800)
+    ///     .repeats(Repeatability::Yearly)
+    ///     .build();
+    /// // 2025-02-28, 2026-02-28, 2027-02-28 and 2028-02-29
+    /// assert_eq!(
+    ///     leap_day.occurrences(1709164801, 4),
+    ///     [
+    ///         Timestamp(1740700800),
+    ///         Timestamp(1772236800),
+    ///         Timestamp(1803772800),
+    ///         Timestamp(1835395200)
+    ///     ]
+    /// );
+    ///
+    /// // 2024-01-31 00:00:00 UTC
+    /// let rent = EventBuilder::new(EventId(2), "Rent", 1706659200)
+    ///     .repeats(Repeatability::Monthly)
+    ///     .max_occurrences(3)
+    ///     .build();
+    /// // 2024-01-31, 2024-02-29 and 2024-03-31, then it runs out
+    /// assert_eq!(
+    ///     rent.occurrences(0, 5),
+    ///     [Timestamp(1706659200), Timestamp(1709164800), Timestamp(1711843200)]
+    /// );
+    ///
+    /// let once = EventBuilder::new(EventId(3), "Once", 1000).build();
+    /// assert_eq!(once.occurrences(1000, 5), [Timestamp(1000)]);
+    /// assert!(once.occurrences(1001, 5).is_empty());
+    /// ```
+    pub fn occurrences<T>(&self, from: T, count: usize) -> Vec<Timestamp>
+    where
+        T: Into<Timestamp>,
+    {
+        let from = from.into();
+        let mut occurrences = vec![];
+        let mut remaining = self.remaining_occurrences();
+        let mut next = Some(self.next_occurence);
+        while let Some(at) = next {
+            if occurrences.len() >= count || remaining == Some(0) {
+                break;
+            }
+            if at >= from {
+                occurrences.push(at);
+            }
+            remaining = remaining.map(|r| r - 1);
+            // Stops instead of looping forever if the repetition doesn't move forward
+            next = self.next_repetition(at).filter(|&n| n > at);
+        }
+        occurrences
+    }
+    // When the next occurence counts as happening, the start of its day for all-day
+    // events
+    pub(crate) fn fires_at(&self) -> u64 {
+        let at = self.next_occurence.0;
+        if !self.all_day {
+            return at;
+        }
+        #[cfg(feature = "chrono")]
+        if let Some(tz) = self.timezone.as_deref().and_then(|tz| tz.parse().ok()) {
+            if let Some(midnight) = local_midnight(at, tz) {
+                return midnight;
+            }
+        }
+        at - at % SECS_PER_DAY
+    }
+    // When listeners deliver the event next, its lead time before the next occurence
+    pub(crate) fn reminder_at(&self) -> u64 {
+        self.fires_at().saturating_sub(self.lead_secs.unwrap_or(0))
+    }
+    // Where the event goes in the event queue, paused events are left out of it
+    pub(crate) fn queued_at(&self) -> Option<u64> {
+        self.enabled.then(|| self.reminder_at())
+    }
+    // Moves the event to a new occurence, keeping its duration
+    pub(crate) fn move_to(&mut self, next_occurence: Timestamp) {
+        if let Some(end) = self.end {
+            let duration = end.0.saturating_sub(self.next_occurence.0);
+            self.end = Some(next_occurence.saturating_add(duration));
+        }
+        self.next_occurence = next_occurence;
+    }
+    // Schedules the event somewhere else, so that it repeats from the new day on
+    pub(crate) fn reschedule_to(&mut self, next_occurence: Timestamp) {
+        self.move_to(next_occurence);
+        self.day_of_month = self.local_date(next_occurence.0).map(|(_, _, day)| day);
+    }
+    // Year, month and day of `at` in the time zone of the event, UTC without the
+    // `chrono` feature or a known time zone
+    fn local_date(&self, at: u64) -> Option<(i64, u32, u32)> {
+        #[cfg(feature = "chrono")]
+        if let Some(tz) = self
+            .timezone
+            .as_deref()
+            .and_then(|tz| tz.parse::<chrono_tz::Tz>().ok())
+        {
+            use chrono::{Datelike, TimeZone};
+
+            let date = tz
+                .timestamp_opt(i64::try_from(at).ok()?, 0)
+                .single()?
+                .date_naive();
+            return Some((i64::from(date.year()), date.month(), date.day()));
+        }
+        Some(civil_from_days((at / SECS_PER_DAY) as i64))
+    }
+    // Day of the month that month based repetitions from `from` land on, the day the
+    // event was scheduled on if `from` is on it or on the last day of a month too
+    // short for it, and the day of `from` otherwise, like after editing it by hand
+    fn repeat_day(&self, from: u64) -> Option<u32> {
+        let (year, month, day) = self.local_date(from)?;
+        Some(match self.day_of_month {
+            Some(scheduled) if scheduled.min(days_in_month(year, month)) == day => scheduled,
+            _ => day,
+        })
     }
 }
 impl PartialEq for Event {
@@ -38,30 +363,409 @@ impl PartialEq for Event {
     }
 }
 impl Eq for Event {}
+#[cfg(feature = "display")]
+impl std::fmt::Display for Event {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        EventFormatter::new().write(self, f)
+    }
+}
 
-#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+/// Configurable formatting for an [`Event`], only available with the `display` feature.
+///
+/// The default build of nari doesn't implement [`Display`](std::fmt::Display) for [`Event`]
+/// as it can be represented in multiple equally valid ways, enabling the `display` feature
+/// implements it with the default `EventFormatter`.
+///
+/// ## Usage
+/// ```
+/// # use nari::models::{event::{EventBuilder, EventFormatter}, EventId, UserId};
+/// let meeting = EventBuilder::new(EventId(1), "Meeting", 123456789)
+///     .description("Weekly sync")
+///     .users([UserId(1), UserId(2)])
+///     .build();
+///
+/// assert_eq!(meeting.to_string(), "Meeting — Weekly sync (fires at 123456789)");
+/// assert_eq!(
+///     EventFormatter::new().priority(true).user_count(true).format(&meeting),
+///     "Meeting — Weekly sync (fires at 123456789) [Low priority, 2 users]"
+/// );
+/// ```
+#[cfg(feature = "display")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct EventFormatter {
+    priority: bool,
+    user_count: bool,
+}
+#[cfg(feature = "display")]
+impl EventFormatter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Whether to include the priority of the event.
+    pub fn priority(mut self, include: bool) -> Self {
+        self.priority = include;
+        self
+    }
+    /// Whether to include the amount of users in the event.
+    pub fn user_count(mut self, include: bool) -> Self {
+        self.user_count = include;
+        self
+    }
+    pub fn format(&self, event: &Event) -> String {
+        let mut formatted = String::new();
+        self.write(event, &mut formatted).unwrap();
+        formatted
+    }
+    fn write<W>(&self, event: &Event, f: &mut W) -> std::fmt::Result
+    where
+        W: std::fmt::Write,
+    {
+        write!(f, "{}", event.name)?;
+        if !event.description.is_empty() {
+            write!(f, " — {}", event.description)?;
+        }
+        write!(f, " (fires at {})", event.next_occurence)?;
+
+        let mut extra = vec![];
+        if self.priority {
+            extra.push(format!("{:?} priority", event.priority));
+        }
+        if self.user_count {
+            extra.push(match event.users.len() {
+                1 => String::from("1 user"),
+                n => format!("{n} users"),
+            });
+        }
+        if !extra.is_empty() {
+            write!(f, " [{}]", extra.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// How often an event repeats.
+///
+/// It can be parsed from and displayed as lowercase names, ignoring the case and any
+/// `-` or `_` when parsing, custom intervals are written as `custom:<seconds>`.
+///
+/// ## Usage
+/// ```
+/// # use nari::models::event::Repeatability;
+/// let repeats: Repeatability = "Weekly".parse().unwrap();
+/// assert_eq!(repeats.to_string(), "weekly");
+///
+/// let repeats: Repeatability = "CUSTOM:5400".parse().unwrap();
+/// assert!(matches!(repeats, Repeatability::Custom { interval_secs: 5400 }));
+/// assert_eq!(repeats.to_string(), "custom:5400");
+///
+/// assert!("fortnightly".parse::<Repeatability>().is_err());
+/// assert!("custom:soon".parse::<Repeatability>().is_err());
+/// ```
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub enum Repeatability {
     Yearly,
+    /// Every two years.
     Biyearly,
     Quarterly,
     Monthly,
+    /// Every two months.
     Bimonthly,
     Weekly,
     Daily,
     Hourly,
+    /// Every `interval_secs` seconds, like every three days or every 90 minutes.
+    ///
+    /// An interval of zero never repeats.
+    Custom {
+        interval_secs: u64,
+    },
     #[default]
     Never,
 }
+impl Repeatability {
+    /// Returns the occurence that comes after the unix timestamp `from`, or `None` if the
+    /// event doesn't repeat or the next occurence can't be represented.
+    ///
+    /// Month based repetitions keep the time of the day and clamp the day to the length
+    /// of the target month, so an event on January 31st repeats monthly on February 28th
+    /// (or 29th). Only the day of `from` is known here, so advancing again from there
+    /// stays on that day, [`Event::next_repetition`] goes back to the 31st instead.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{event::Repeatability, Timestamp};
+    /// // 2024-02-29 00:00:00 UTC
+    /// let leap_day = Timestamp(1709164800);
+    /// // 2025-02-28 00:00:00 UTC
+    /// assert_eq!(Repeatability::Yearly.advance(leap_day), Some(Timestamp(1740700800)));
+    /// // 2026-02-28 00:00:00 UTC
+    /// assert_eq!(Repeatability::Biyearly.advance(leap_day), Some(Timestamp(1772236800)));
+    /// assert_eq!(Repeatability::Daily.advance(leap_day), Some(leap_day + 24 * 60 * 60));
+    /// assert_eq!(Repeatability::Never.advance(leap_day), None);
+    ///
+    /// // 2024-01-30 00:00:00 UTC
+    /// let end_of_january = 1706572800;
+    /// let every_three_days = Repeatability::Custom { interval_secs: 3 * 24 * 60 * 60 };
+    /// // 2024-02-02 00:00:00 UTC
+    /// assert_eq!(every_three_days.advance(end_of_january), Some(Timestamp(1706832000)));
+    /// ```
+    pub fn advance<T>(&self, from: T) -> Option<Timestamp>
+    where
+        T: Into<Timestamp>,
+    {
+        let from = from.into();
+        let (_, _, day) = civil_from_days((from.0 / SECS_PER_DAY) as i64);
+        self.advance_to_day(from, day)
+    }
+    // Like `advance`, but month based repetitions land on `day` of the target month,
+    // or its last day if it is shorter
+    pub(crate) fn advance_to_day(&self, from: Timestamp, day: u32) -> Option<Timestamp> {
+        let from = from.0;
+        let next = match self {
+            Self::Yearly => add_months(from, 12, day),
+            Self::Biyearly => add_months(from, 24, day),
+            Self::Quarterly => add_months(from, 3, day),
+            Self::Monthly => add_months(from, 1, day),
+            Self::Bimonthly => add_months(from, 2, day),
+            Self::Weekly => from.checked_add(7 * SECS_PER_DAY),
+            Self::Daily => from.checked_add(SECS_PER_DAY),
+            Self::Hourly => from.checked_add(SECS_PER_HOUR),
+            Self::Custom { interval_secs: 0 } => None,
+            Self::Custom { interval_secs } => from.checked_add(*interval_secs),
+            Self::Never => None,
+        };
+        next.map(Timestamp)
+    }
+    /// Returns the occurence that comes after the unix timestamp `from` like
+    /// [`advance`](Repeatability::advance) does, but keeping the wall-clock time in the
+    /// time zone `tz`, only available with the `chrono` feature.
+    ///
+    /// Hourly and custom repetitions are unaffected by the time zone. When the wall-clock time
+    /// happens twice the earliest is taken, and when it is skipped an hour later is taken.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{event::Repeatability, Timestamp};
+    /// use chrono_tz::Europe::Berlin;
+    /// // 2024-03-30 09:00:00 in Berlin, the day before switching to summer time
+    /// let before = Timestamp(1711785600);
+    /// // 2024-03-31 09:00:00 in Berlin, only 23 hours later
+    /// assert_eq!(Repeatability::Daily.advance_in(before, Berlin), Some(before + 23 * 60 * 60));
+    /// assert_eq!(Repeatability::Daily.advance(before), Some(before + 24 * 60 * 60));
+    /// ```
+    #[cfg(feature = "chrono")]
+    pub fn advance_in<T>(&self, from: T, tz: chrono_tz::Tz) -> Option<Timestamp>
+    where
+        T: Into<Timestamp>,
+    {
+        use chrono::{Datelike, TimeZone};
+
+        let from = from.into();
+        let day = tz
+            .timestamp_opt(i64::try_from(from.0).ok()?, 0)
+            .single()?
+            .day();
+        self.advance_in_to_day(from, tz, day)
+    }
+    // Like `advance_in`, but month based repetitions land on `day` of the target
+    // month, or its last day if it is shorter
+    #[cfg(feature = "chrono")]
+    pub(crate) fn advance_in_to_day(
+        &self,
+        from: Timestamp,
+        tz: chrono_tz::Tz,
+        day: u32,
+    ) -> Option<Timestamp> {
+        use chrono::{Datelike, Days, Months, TimeZone};
+
+        let local = tz
+            .timestamp_opt(i64::try_from(from.0).ok()?, 0)
+            .single()?
+            .naive_local();
+        let add_months = |months: u32| {
+            let first = local
+                .date()
+                .with_day(1)?
+                .checked_add_months(Months::new(months))?;
+            let last = days_in_month(i64::from(first.year()), first.month());
+            Some(first.with_day(day.min(last))?.and_time(local.time()))
+        };
+        let next = match self {
+            Self::Yearly => add_months(12),
+            Self::Biyearly => add_months(24),
+            Self::Quarterly => add_months(3),
+            Self::Monthly => add_months(1),
+            Self::Bimonthly => add_months(2),
+            Self::Weekly => local.checked_add_days(Days::new(7)),
+            Self::Daily => local.checked_add_days(Days::new(1)),
+            Self::Hourly | Self::Custom { .. } => return self.advance(from),
+            Self::Never => None,
+        }?;
+        let next = match tz.from_local_datetime(&next).earliest() {
+            Some(next) => next,
+            None => tz
+                .from_local_datetime(&(next + chrono::Duration::hours(1)))
+                .earliest()?,
+        };
+        u64::try_from(next.timestamp()).ok().map(Timestamp)
+    }
+}
+
+impl std::fmt::Display for Repeatability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Yearly => "yearly",
+            Self::Biyearly => "biyearly",
+            Self::Quarterly => "quarterly",
+            Self::Monthly => "monthly",
+            Self::Bimonthly => "bimonthly",
+            Self::Weekly => "weekly",
+            Self::Daily => "daily",
+            Self::Hourly => "hourly",
+            Self::Custom { interval_secs } => return write!(f, "custom:{interval_secs}"),
+            Self::Never => "never",
+        };
+        f.write_str(name)
+    }
+}
+impl FromStr for Repeatability {
+    type Err = ParseVariantError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = normalize_variant(s);
+        if let Some(secs) = name.strip_prefix("custom:") {
+            return match secs.parse() {
+                Ok(interval_secs) => Ok(Self::Custom { interval_secs }),
+                Err(_) => Err(ParseVariantError::new("repeatability", s)),
+            };
+        }
+        match name.as_str() {
+            "yearly" => Ok(Self::Yearly),
+            "biyearly" => Ok(Self::Biyearly),
+            "quarterly" => Ok(Self::Quarterly),
+            "monthly" => Ok(Self::Monthly),
+            "bimonthly" => Ok(Self::Bimonthly),
+            "weekly" => Ok(Self::Weekly),
+            "daily" => Ok(Self::Daily),
+            "hourly" => Ok(Self::Hourly),
+            "never" => Ok(Self::Never),
+            _ => Err(ParseVariantError::new("repeatability", s)),
+        }
+    }
+}
 
-#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+/// How important an event is.
+///
+/// Priorities are ordered by urgency, [`Minimal`](Priority::Minimal) being the
+/// lowest and [`Urgent`](Priority::Urgent) the greatest. They can be parsed from and
+/// displayed as lowercase names, ignoring the case and any `-` or `_` when parsing.
+///
+/// ## Usage
+/// ```
+/// # use nari::models::event::Priority;
+/// assert!(Priority::Urgent > Priority::Minimal);
+///
+/// let mut priorities = vec![Priority::Low, Priority::Urgent, Priority::Minimal, Priority::High];
+/// priorities.sort();
+/// assert_eq!(
+///     priorities,
+///     [Priority::Minimal, Priority::Low, Priority::High, Priority::Urgent]
+/// );
+///
+/// assert_eq!("Very-High".parse(), Ok(Priority::VeryHigh));
+/// assert_eq!(Priority::VeryHigh.to_string(), "veryhigh");
+/// let err = "critical".parse::<Priority>().unwrap_err();
+/// assert_eq!(err.to_string(), "`critical` is not a valid priority");
+/// ```
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Priority {
-    Urgent,
-    VeryHigh,
-    High,
-    Medium,
+    Minimal,
     #[default]
     Low,
-    Minimal,
+    Medium,
+    High,
+    VeryHigh,
+    Urgent,
+}
+impl Priority {
+    /// Returns the priority one step above, staying at [`Urgent`](Priority::Urgent).
+    ///
+    /// The steps are `Minimal` → `Low` → `Medium` → `High` → `VeryHigh` → `Urgent`.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::event::Priority;
+    /// assert_eq!(Priority::Minimal.raised(), Priority::Low);
+    /// assert_eq!(Priority::High.raised(), Priority::VeryHigh);
+    /// assert_eq!(Priority::Urgent.raised(), Priority::Urgent);
+    /// ```
+    pub fn raised(&self) -> Self {
+        match self {
+            Self::Minimal => Self::Low,
+            Self::Low => Self::Medium,
+            Self::Medium => Self::High,
+            Self::High => Self::VeryHigh,
+            Self::VeryHigh | Self::Urgent => Self::Urgent,
+        }
+    }
+}
+impl std::fmt::Display for Priority {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Minimal => "minimal",
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+            Self::VeryHigh => "veryhigh",
+            Self::Urgent => "urgent",
+        })
+    }
+}
+impl FromStr for Priority {
+    type Err = ParseVariantError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match normalize_variant(s).as_str() {
+            "minimal" => Ok(Self::Minimal),
+            "low" => Ok(Self::Low),
+            "medium" => Ok(Self::Medium),
+            "high" => Ok(Self::High),
+            "veryhigh" => Ok(Self::VeryHigh),
+            "urgent" => Ok(Self::Urgent),
+            _ => Err(ParseVariantError::new("priority", s)),
+        }
+    }
+}
+
+/// Why a [`Priority`] or [`Repeatability`] couldn't be parsed from a string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseVariantError {
+    kind: &'static str,
+    value: String,
+}
+impl ParseVariantError {
+    fn new(kind: &'static str, value: &str) -> Self {
+        Self {
+            kind,
+            value: String::from(value),
+        }
+    }
+}
+impl std::fmt::Display for ParseVariantError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "`{}` is not a valid {}", self.value, self.kind)
+    }
+}
+impl std::error::Error for ParseVariantError {}
+
+/// Lowercases a variant name and drops the `-` and `_` in it, so that `very-high`,
+/// `Very_High` and `VeryHigh` are all the same.
+fn normalize_variant(s: &str) -> String {
+    s.trim()
+        .chars()
+        .filter(|c| *c != '-' && *c != '_')
+        .flat_map(char::to_lowercase)
+        .collect()
 }
 
 #[derive(Debug, Default)]
@@ -69,17 +773,30 @@ pub struct EventBuilder {
     id: EventId,
     name: String,
     description: String,
-    next_occurence: u64,
+    next_occurence: Timestamp,
     users: HashSet<UserId>,
     repeats: Repeatability,
     priority: Priority,
+    end: Option<Timestamp>,
+    timezone: Option<String>,
+    tags: HashSet<String>,
+    metadata: BTreeMap<String, String>,
+    max_occurrences: Option<u32>,
+    allow_past: bool,
+    not_before: Option<Timestamp>,
+    lead_secs: Option<u64>,
+    all_day: bool,
+    depends_on: Option<EventId>,
 }
 impl EventBuilder {
-    pub fn new(id: EventId, name: &str, next_occurence: u64) -> Self {
+    pub fn new<T>(id: EventId, name: &str, next_occurence: T) -> Self
+    where
+        T: Into<Timestamp>,
+    {
         Self {
             id,
             name: String::from(name),
-            next_occurence,
+            next_occurence: next_occurence.into(),
             ..Self::default()
         }
     }
@@ -87,6 +804,25 @@ impl EventBuilder {
         self.description = String::from(desc);
         self
     }
+    /// Adds users to the event, from any collection of [`UserId`].
+    ///
+    /// ## Usage
+    /// ```
+    /// # use std::collections::HashSet;
+    /// # use nari::models::{event::EventBuilder, EventId, UserId};
+    /// let from_array = EventBuilder::new(EventId(1), "Lunch", 1000)
+    ///     .users([UserId(1), UserId(2)])
+    ///     .build();
+    /// let from_vec = EventBuilder::new(EventId(2), "Lunch", 1000)
+    ///     .users(vec![UserId(1), UserId(2)])
+    ///     .build();
+    /// let from_set = EventBuilder::new(EventId(3), "Lunch", 1000)
+    ///     .users(HashSet::from([UserId(1), UserId(2)]))
+    ///     .build();
+    ///
+    /// assert_eq!(from_array.users, from_vec.users);
+    /// assert_eq!(from_vec.users, from_set.users);
+    /// ```
     pub fn users<I>(mut self, users: I) -> Self
     where
         I: IntoIterator<Item = UserId>,
@@ -104,8 +840,331 @@ impl EventBuilder {
         self.priority = priority;
         self
     }
+    /// Adds a tag to the event.
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.tags.insert(String::from(tag));
+        self
+    }
+    /// Adds several tags to the event.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{event::EventBuilder, EventId};
+    /// let review = EventBuilder::new(EventId(1), "Code review", 1000)
+    ///     .tag("work")
+    ///     .tags(["weekly", "work"])
+    ///     .build();
+    /// assert_eq!(review.tags.len(), 2);
+    /// assert!(review.tags.contains("weekly"));
+    /// ```
+    pub fn tags<I, S>(mut self, tags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.tags.extend(tags.into_iter().map(Into::into));
+        self
+    }
+    /// Attaches `value` to the event under `key`, replacing any previous value, see
+    /// [`Event::metadata`].
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{event::EventBuilder, Database, EventId};
+    /// # fn main() -> std::io::Result<()> {
+    /// let db = Database::new(std::env::temp_dir().join("nari_metadata"))?;
+    /// EventBuilder::new(EventId(1), "Stand-up", 1000)
+    ///     .meta("url", "https://meet.example.com/standup")
+    ///     .meta("color", "#ff8800")
+    ///     .build()
+    ///     .save_to_db(&db)?;
+    ///
+    /// let standup = db.fetch_event(EventId(1))?.unwrap();
+    /// assert_eq!(standup.metadata["color"], "#ff8800");
+    /// assert_eq!(standup.metadata.len(), 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn meta<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+    /// Makes a repeating event stop after firing `max` times.
+    pub fn max_occurrences(mut self, max: u32) -> Self {
+        self.max_occurrences = Some(max);
+        self
+    }
+    /// Sets the unix timestamp of when the event ends.
+    pub fn end<T>(mut self, end: T) -> Self
+    where
+        T: Into<Timestamp>,
+    {
+        self.end = Some(end.into());
+        self
+    }
+    /// Sets the end of the event to `secs` seconds after its next occurence.
+    pub fn duration(mut self, secs: u64) -> Self {
+        self.end = Some(self.next_occurence.saturating_add(secs));
+        self
+    }
+    /// Sets the next occurence to `start` and schedules the event in its time zone,
+    /// only available with the `chrono` feature.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{event::{EventBuilder, Repeatability}, EventId, Timestamp};
+    /// use chrono::TimeZone;
+    /// use chrono_tz::Europe::Berlin;
+    ///
+    /// let start = Berlin.with_ymd_and_hms(2024, 3, 30, 9, 0, 0).unwrap();
+    /// let standup = EventBuilder::new(EventId(1), "Standup", 0)
+    ///     .starts_at(start)
+    ///     .repeats(Repeatability::Daily)
+    ///     .build();
+    /// assert_eq!(standup.timezone.as_deref(), Some("Europe/Berlin"));
+    ///
+    /// let next = Berlin.with_ymd_and_hms(2024, 3, 31, 9, 0, 0).unwrap();
+    /// let expected = Timestamp(next.timestamp() as u64);
+    /// assert_eq!(standup.next_repetition(standup.next_occurence), Some(expected));
+    /// ```
+    #[cfg(feature = "chrono")]
+    pub fn starts_at(mut self, start: chrono::DateTime<chrono_tz::Tz>) -> Self {
+        self.next_occurence = Timestamp(u64::try_from(start.timestamp()).unwrap_or(0));
+        self.timezone = Some(start.timezone().name().to_string());
+        self
+    }
+    /// Makes [`build_validated`](EventBuilder::build_validated) accept a next occurence
+    /// in the past.
+    pub fn allow_past(mut self) -> Self {
+        self.allow_past = true;
+        self
+    }
+    /// Sets the next occurence to `d` from the current time, rounded down to the
+    /// second.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use std::time::Duration;
+    /// # use nari::models::{event::EventBuilder, EventId, Timestamp};
+    /// let before = Timestamp::now();
+    /// let tea = EventBuilder::new(EventId(1), "Tea time", 0)
+    ///     .in_duration(Duration::from_secs(15 * 60))
+    ///     .build();
+    /// assert!(tea.next_occurence >= before + 15 * 60);
+    /// assert!(tea.next_occurence <= Timestamp::now() + 15 * 60);
+    /// ```
+    pub fn in_duration(mut self, d: Duration) -> Self {
+        self.next_occurence = Timestamp::now().saturating_add(d.as_secs());
+        self
+    }
+    /// Makes [`build_validated`](EventBuilder::build_validated) reject a next occurence
+    /// before the unix timestamp `floor` instead of before the current time.
+    pub fn not_before<T>(mut self, floor: T) -> Self
+    where
+        T: Into<Timestamp>,
+    {
+        self.not_before = Some(floor.into());
+        self
+    }
+    /// Makes listeners deliver the event `secs` seconds before each occurence, as a
+    /// reminder, see [`LeadPolicy`] for what happens at the occurence itself.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use std::time::{Duration, SystemTime, UNIX_EPOCH};
+    /// # use nari::models::{event::{EventListener, FiredEvent}, Database, EventId};
+    /// # use tokio::{sync::mpsc, time::timeout};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = std::env::temp_dir().join("nari_lead_secs");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::new(&dir)?;
+    /// let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    /// db.build_event(EventId(1), "Train leaves", now + 2)
+    ///     .lead_secs(1)
+    ///     .build()
+    ///     .save_to_db(&db)?;
+    ///
+    /// let (tx, mut rx) = mpsc::channel::<FiredEvent>(16);
+    /// let listener = EventListener::builder().database(&db).refresh_rate(100).build(tx)?;
+    /// tokio::spawn(listener.start());
+    ///
+    /// let reminder = timeout(Duration::from_secs(5), rx.recv()).await?.unwrap();
+    /// assert!(reminder.is_reminder);
+    /// let departure = timeout(Duration::from_secs(5), rx.recv()).await?.unwrap();
+    /// assert!(!departure.is_reminder);
+    /// # std::fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn lead_secs(mut self, secs: u64) -> Self {
+        self.lead_secs = Some(secs);
+        self
+    }
+    /// Makes the event last the whole day, see [`Event::all_day`].
+    ///
+    /// ## Usage
+    /// ```
+    /// # use std::time::{Duration, SystemTime, UNIX_EPOCH};
+    /// # use nari::models::{event::{EventListener, FiredEvent, Repeatability}, Database, EventId};
+    /// # use tokio::{sync::mpsc, time::timeout};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = std::env::temp_dir().join("nari_all_day");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::new(&dir)?;
+    /// let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    /// // The last second of today, UTC
+    /// let tonight = now - now % (24 * 60 * 60) + 24 * 60 * 60 - 1;
+    /// db.build_event(EventId(1), "Anniversary", tonight)
+    ///     .repeats(Repeatability::Yearly)
+    ///     .all_day()
+    ///     .build()
+    ///     .save_to_db(&db)?;
+    ///
+    /// let (tx, mut rx) = mpsc::channel::<FiredEvent>(16);
+    /// let listener = EventListener::builder().database(&db).refresh_rate(100).build(tx)?;
+    /// tokio::spawn(listener.start());
+    ///
+    /// // Fired right away since today already started
+    /// let fired = timeout(Duration::from_secs(5), rx.recv()).await?.unwrap();
+    /// assert_eq!(fired.event.id, EventId(1));
+    ///
+    /// // Rescheduled to the same date next year
+    /// let next_year = Repeatability::Yearly.advance(tonight).unwrap();
+    /// let anniversary = db.fetch_event(EventId(1))?.unwrap();
+    /// assert_eq!(anniversary.next_occurence, next_year);
+    /// # std::fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn all_day(mut self) -> Self {
+        self.all_day = true;
+        self
+    }
+    /// Makes listeners hold the event back until `id` has fired, so that it fires at
+    /// its next occurence or right after `id`, whichever comes last.
+    ///
+    /// Only the events queued when `id` fires are released, one added or resumed
+    /// later waits for `id` to fire again. Once released the event is never held back
+    /// again, not even as it repeats, until it leaves the queue. Reminders don't count
+    /// as firing. The releases are recorded in the state file of the listener, see
+    /// [`EventListener::with_state_file`], so the dependency has to be fired by the
+    /// same listener, or one sharing its state file after it. If `id` doesn't exist,
+    /// or is deleted while the event waits on it, the event is released and fires as
+    /// if it had no dependency.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use std::time::{Duration, SystemTime, UNIX_EPOCH};
+    /// # use nari::models::{event::{EventListener, FiredEvent}, Database, EventId};
+    /// # use tokio::{sync::mpsc, time::timeout};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let dir = std::env::temp_dir().join("nari_depends_on");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::new(&dir)?;
+    /// let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    /// db.build_event(EventId(1), "Build", now + 2).build().save_to_db(&db)?;
+    /// // Due first, but waits for the build
+    /// db.build_event(EventId(2), "Deploy", now)
+    ///     .depends_on(EventId(1))
+    ///     .build()
+    ///     .save_to_db(&db)?;
+    /// // Its dependency doesn't exist, so nothing holds it back
+    /// db.build_event(EventId(3), "Cleanup", now + 1)
+    ///     .depends_on(EventId(99))
+    ///     .build()
+    ///     .save_to_db(&db)?;
+    ///
+    /// let (tx, mut rx) = mpsc::channel::<FiredEvent>(16);
+    /// let listener = EventListener::builder().database(&db).refresh_rate(100).build(tx)?;
+    /// tokio::spawn(listener.start());
+    ///
+    /// let mut order = vec![];
+    /// for _ in 0..3 {
+    ///     let fired = timeout(Duration::from_secs(5), rx.recv()).await?.unwrap();
+    ///     order.push(fired.event.id);
+    /// }
+    /// assert_eq!(order, [EventId(3), EventId(1), EventId(2)]);
+    /// # std::fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn depends_on(mut self, id: EventId) -> Self {
+        self.depends_on = Some(id);
+        self
+    }
+    /// Builds the event like [`build`](EventBuilder::build), but first checks that its
+    /// next occurence is a sensible unix timestamp in seconds.
+    ///
+    /// It fails if the next occurence is before the current time, or the one given to
+    /// [`not_before`](EventBuilder::not_before), unless [`allow_past`](EventBuilder::allow_past)
+    /// is used. It also fails if it is so far in the future that it was most likely
+    /// given in milliseconds, see [`EventError::LikelyMilliseconds`].
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{event::{EventBuilder, EventError}, EventId};
+    /// let result = EventBuilder::new(EventId(1), "Meeting", 1000).build_validated();
+    /// assert!(matches!(result, Err(EventError::InThePast { .. })));
+    ///
+    /// let meeting = EventBuilder::new(EventId(1), "Meeting", 1000)
+    ///     .allow_past()
+    ///     .build_validated();
+    /// assert!(meeting.is_ok());
+    ///
+    /// // 2023-03-05 in milliseconds
+    /// let result = EventBuilder::new(EventId(1), "Meeting", 1678000000000)
+    ///     .not_before(0)
+    ///     .build_validated();
+    /// assert!(matches!(result, Err(EventError::LikelyMilliseconds { .. })));
+    /// ```
+    pub fn build_validated(self) -> Result<Event, EventError> {
+        let next_occurence = self.next_occurence.0;
+        if next_occurence >= MILLISECONDS_THRESHOLD {
+            return Err(EventError::LikelyMilliseconds { next_occurence });
+        }
+        if !self.allow_past {
+            let floor = self.not_before.unwrap_or_else(Timestamp::now).0;
+            if next_occurence < floor {
+                return Err(EventError::InThePast {
+                    next_occurence,
+                    floor,
+                });
+            }
+        }
+        Ok(self.build())
+    }
+    /// Builds the event like [`build`](EventBuilder::build), but first trims the
+    /// whitespace around its name and fails with [`EventError::EmptyName`] if nothing
+    /// is left.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{event::{EventBuilder, EventError}, EventId};
+    /// let standup = EventBuilder::new(EventId(1), "  Standup\n", 1000).try_build();
+    /// assert_eq!(standup.unwrap().name, "Standup");
+    ///
+    /// let empty = EventBuilder::new(EventId(2), "", 1000).try_build();
+    /// assert_eq!(empty.unwrap_err(), EventError::EmptyName);
+    /// let blank = EventBuilder::new(EventId(3), " \t ", 1000).try_build();
+    /// assert_eq!(blank.unwrap_err(), EventError::EmptyName);
+    /// ```
+    pub fn try_build(mut self) -> Result<Event, EventError> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Err(EventError::EmptyName);
+        }
+        self.name = String::from(name);
+        Ok(self.build())
+    }
     pub fn build(self) -> Event {
-        Event {
+        let mut event = Event {
             id: self.id,
             name: self.name,
             description: self.description,
@@ -113,93 +1172,1665 @@ impl EventBuilder {
             users: self.users,
             repeats: self.repeats,
             priority: self.priority,
+            end: self.end,
+            timezone: self.timezone,
+            tags: self.tags,
+            metadata: self.metadata,
+            max_occurrences: self.max_occurrences,
+            occurrences_fired: 0,
+            lead_secs: self.lead_secs,
+            enabled: true,
+            all_day: self.all_day,
+            depends_on: self.depends_on,
+            day_of_month: None,
+            version: SCHEMA_VERSION,
+        };
+        event.day_of_month = event
+            .local_date(event.next_occurence.0)
+            .map(|(_, _, day)| day);
+        event
+    }
+}
+/// Returns the unix timestamp of the midnight starting the day of `at` in `tz`.
+#[cfg(feature = "chrono")]
+fn local_midnight(at: u64, tz: chrono_tz::Tz) -> Option<u64> {
+    use chrono::TimeZone;
+
+    let date = tz
+        .timestamp_opt(i64::try_from(at).ok()?, 0)
+        .single()?
+        .date_naive();
+    // Days starting with a DST gap begin at the first valid time
+    let midnight = (0..24)
+        .filter_map(|hour| date.and_hms_opt(hour, 0, 0))
+        .find_map(|start| tz.from_local_datetime(&start).earliest())?;
+    u64::try_from(midnight.timestamp()).ok()
+}
+/// Events saved before they could be paused are enabled.
+fn enabled_by_default() -> bool {
+    true
+}
+
+/// Timestamps from this one on are taken as given in milliseconds, in seconds it would
+/// be somewhere in the year 5138.
+const MILLISECONDS_THRESHOLD: u64 = 100_000_000_000;
+
+/// Why [`EventBuilder::build_validated`] or [`EventBuilder::try_build`] rejected an event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventError {
+    /// The next occurence is before `floor`, which is the current time unless
+    /// [`EventBuilder::not_before`] was used.
+    InThePast { next_occurence: u64, floor: u64 },
+    /// The next occurence is so far in the future that it was most likely given in
+    /// milliseconds instead of seconds.
+    LikelyMilliseconds { next_occurence: u64 },
+    /// The name is empty or only has whitespace, see [`EventBuilder::try_build`].
+    EmptyName,
+}
+impl std::fmt::Display for EventError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InThePast {
+                next_occurence,
+                floor,
+            } => write!(f, "the next occurence {next_occurence} is before {floor}"),
+            Self::LikelyMilliseconds { next_occurence } => write!(
+                f,
+                "the next occurence {next_occurence} looks like milliseconds, expected seconds"
+            ),
+            Self::EmptyName => f.write_str("the name of the event is empty"),
+        }
+    }
+}
+impl std::error::Error for EventError {}
+
+/// What an [`EventListener`] does with the events whose time passed while it wasn't running.
+///
+/// See [`EventListener::with_state_file`] for how the listener tells which events it missed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CatchUp {
+    /// Missed events are not delivered, repeating events are still rescheduled.
+    DropMissed,
+    /// Missed events are delivered as soon as the listener starts, just like any other event.
+    #[default]
+    FireImmediately,
+    /// Missed events are delivered as soon as the listener starts, with
+    /// [`FiredEvent::late_by`] telling how late they are.
+    FireWithFlag,
+}
+
+/// What an [`EventListener`] does at the occurence of an event that was already
+/// delivered as a reminder, see [`Event::lead_secs`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LeadPolicy {
+    /// The event is delivered again at its occurence, this time not as a reminder.
+    #[default]
+    RemindAndFire,
+    /// The reminder counts as the occurence, the event is not delivered again.
+    RemindOnly,
+}
+
+/// An event delivered by an [`EventListener`].
+///
+/// Listeners can deliver any type that implements `From<FiredEvent>`, like [`Event`] or [`EventId`],
+/// see [`EventListener::from_sender`].
+#[non_exhaustive]
+#[derive(Debug, Clone)]
+pub struct FiredEvent {
+    pub event: Event,
+    /// Seconds between the event's occurence and its delivery, only set for missed
+    /// events when using [`CatchUp::FireWithFlag`].
+    pub late_by: Option<u64>,
+    /// Whether the event is delivered ahead of its occurence because of its
+    /// [`lead_secs`](Event::lead_secs), instead of at the occurence itself.
+    pub is_reminder: bool,
+}
+impl From<FiredEvent> for Event {
+    fn from(fired: FiredEvent) -> Self {
+        fired.event
+    }
+}
+impl From<FiredEvent> for EventId {
+    fn from(fired: FiredEvent) -> Self {
+        fired.event.id
+    }
+}
+
+/// What an [`EventListener`] did on one of its checks for events, see
+/// [`EventListener::with_stats`].
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListenerStats {
+    /// How many events were delivered, including the ones a broadcast listener
+    /// delivered to nobody.
+    pub fired: usize,
+    /// When the check was made.
+    pub tick_at: Timestamp,
+}
+
+/// Where an [`EventListener`] delivers its events.
+#[derive(Debug)]
+enum Sink<T> {
+    Mpsc(mpsc::Sender<T>),
+    Broadcast(broadcast::Sender<T>),
+    /// Events are only delivered to the callback given to `run_with`.
+    Detached,
+}
+impl<T> Sink<T> {
+    /// Delivers `value`, returns false if it can never be received by anyone.
+    async fn send(&self, value: T) -> bool {
+        match self {
+            Sink::Mpsc(sender) => sender.send(value).await.is_ok(),
+            // Having no subscribers right now is fine, more can subscribe later
+            Sink::Broadcast(sender) => {
+                let _ = sender.send(value);
+                true
+            }
+            Sink::Detached => true,
         }
     }
 }
+
+/// The lowest refresh rate, in milliseconds, an [`EventListener`] runs with.
+///
+/// Smaller values given to the constructors are raised to it, as a zero interval
+/// would keep the listener spinning.
+///
+/// ```
+/// # use nari::models::event::{Event, EventListener, MIN_REFRESH_RATE};
+/// # use nari::models::NariError;
+/// # use tokio::sync::mpsc;
+/// let (tx, _rx) = mpsc::channel(16);
+/// let listener = EventListener::new(tx, 0, "./db");
+/// assert_eq!(listener.refresh_rate(), MIN_REFRESH_RATE);
+///
+/// let (tx, _rx) = mpsc::channel::<Event>(16);
+/// let built = EventListener::builder().refresh_rate(0).build(tx);
+/// assert!(matches!(built, Err(NariError::InvalidInput(_))));
+/// ```
+pub const MIN_REFRESH_RATE: u64 = 1;
+
+/// Raises `refresh_rate` to [`MIN_REFRESH_RATE`], warning about it when it was lower.
+pub(super) fn clamp_refresh_rate(refresh_rate: u64) -> u64 {
+    if refresh_rate < MIN_REFRESH_RATE {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(
+            refresh_rate,
+            min = MIN_REFRESH_RATE,
+            "refresh rate too low, raised to the minimum"
+        );
+    }
+    refresh_rate.max(MIN_REFRESH_RATE)
+}
+
+/// Where the database of a listener is and how to read it, shared by
+/// [`EventListener`], [`SyncEventListener`] and [`EventListenerBuilder`].
+#[derive(Debug, Clone)]
+struct ListenerConfig {
+    base_path: PathBuf,
+    // Found from `base_path` and `format` unless taken from a database
+    cache_path: Option<PathBuf>,
+    // `STATE_FILE_NAME` inside of `base_path` unless set
+    state_path: Option<PathBuf>,
+    catch_up: CatchUp,
+    poll_every: Option<u32>,
+    lock_mode: LockMode,
+    format: SerdeFormat,
+    // Only set from a database, to decrypt its files
+    codec: Codec,
+    // Only set from a database, to find its events
+    layout: Layout,
+    shards: u64,
+    tolerance_ms: u64,
+    lead_policy: LeadPolicy,
+}
+impl ListenerConfig {
+    fn new(base_path: &Path) -> Self {
+        Self {
+            base_path: base_path.to_path_buf(),
+            cache_path: None,
+            state_path: None,
+            catch_up: CatchUp::default(),
+            poll_every: None,
+            lock_mode: LockMode::default(),
+            format: SerdeFormat::default(),
+            codec: Codec::default(),
+            layout: Layout::default(),
+            shards: 0,
+            tolerance_ms: 0,
+            lead_policy: LeadPolicy::default(),
+        }
+    }
+    fn for_database(db: &Database) -> Self {
+        let mut config = Self::new(db.base_path());
+        config.set_database(db);
+        config
+    }
+    /// Takes the location of the files of `db`, their format and how to wait for its
+    /// locks from it, keeping the rest.
+    fn set_database(&mut self, db: &Database) {
+        self.base_path = db.base_path().to_path_buf();
+        self.cache_path = Some(db.cache_path());
+        self.lock_mode = db.lock_mode();
+        self.format = db.format();
+        self.codec = db.codec();
+        self.layout = db.layout();
+        self.shards = db.shards();
+    }
+}
+
 #[non_exhaustive]
 #[derive(Debug)]
-pub struct EventListener {
-    sender: mpsc::Sender<Event>,
+pub struct EventListener<T = Event> {
+    sink: Sink<T>,
     refresh_rate: u64,
+    shutdown: CancellationToken,
+    stats: Option<mpsc::Sender<ListenerStats>>,
+    config: ListenerConfig,
 }
 impl EventListener {
-    pub fn new(sender: mpsc::Sender<Event>, refresh_rate: u64) -> Self {
+    /// Creates a listener for the database found at `base_path`, the same path
+    /// given to [`Database::new`], that checks for events every `refresh_rate`
+    /// milliseconds.
+    ///
+    /// A `refresh_rate` below [`MIN_REFRESH_RATE`] is raised to it, use
+    /// [`EventListener::builder`] to have it rejected instead.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use std::time::{SystemTime, UNIX_EPOCH};
+    /// # use nari::models::{event::EventListener, Database, EventId};
+    /// # use tokio::sync::mpsc;
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let dir = std::env::temp_dir().join("nari_listener");
+    /// let db = Database::new(&dir).unwrap();
+    /// # let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    /// db.build_event(EventId(1), "Tea time", now + 1).build().save_to_db(&db).unwrap();
+    ///
+    /// let (tx, mut rx) = mpsc::channel(16);
+    /// tokio::spawn(EventListener::new(tx, 100, &dir).start());
+    /// assert_eq!(rx.recv().await.unwrap().id, EventId(1));
+    /// # }
+    /// ```
+    pub fn new<P>(sender: mpsc::Sender<Event>, refresh_rate: u64, base_path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        Self::from_sender(sender, refresh_rate, base_path)
+    }
+    /// Creates a listener for `db` just like [`new`](EventListener::new), taking the
+    /// location of its files, their format and how to wait for its locks from it.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::{event::EventListener, Database, LockMode};
+    /// # use std::time::Duration;
+    /// # use tokio::sync::mpsc;
+    /// # #[tokio::main]
+    /// # async fn main() -> std::io::Result<()> {
+    /// let db = Database::with_cache_name("./db", "work_cache.ron")?.with_lock_mode(LockMode::Retry {
+    ///     timeout: Duration::from_secs(1),
+    /// });
+    /// let (tx, mut rx) = mpsc::channel(16);
+    /// tokio::spawn(EventListener::for_database(tx, 500, &db).start());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn for_database(sender: mpsc::Sender<Event>, refresh_rate: u64, db: &Database) -> Self {
+        Self::with_sink(
+            Sink::Mpsc(sender),
+            refresh_rate,
+            ListenerConfig::for_database(db),
+        )
+    }
+    /// Creates a listener that delivers every event to all the receivers subscribed
+    /// to `sender`, so that several tasks can each get every event.
+    ///
+    /// More receivers can be added at any time with [`broadcast::Sender::subscribe`],
+    /// they only get the events fired after subscribing. Events fired while nobody
+    /// is subscribed are dropped.
+    ///
+    /// A subscriber that falls more than the channel's capacity behind loses the oldest
+    /// events, its next `recv` returns [`broadcast::error::RecvError::Lagged`] with how
+    /// many were skipped, and the following calls continue from the oldest event still
+    /// held by the channel. The listener and the other subscribers are never slowed down
+    /// by a lagging one.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::event::EventListener;
+    /// use tokio::sync::broadcast::{self, error::RecvError};
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let (tx, mut notifier) = broadcast::channel(16);
+    /// let mut logger = tx.subscribe();
+    /// tokio::spawn(EventListener::new_broadcast(tx, 500, "./db").start());
+    ///
+    /// tokio::spawn(async move {
+    ///     loop {
+    ///         match logger.recv().await {
+    ///             Ok(event) => println!("{} fired", event.name),
+    ///             Err(RecvError::Lagged(skipped)) => println!("Missed {skipped} events"),
+    ///             Err(RecvError::Closed) => break,
+    ///         }
+    ///     }
+    /// });
+    /// while let Ok(event) = notifier.recv().await {
+    ///     println!("Reminder: {}", event.name);
+    /// }
+    /// # }
+    /// ```
+    pub fn new_broadcast<P>(
+        sender: broadcast::Sender<Event>,
+        refresh_rate: u64,
+        base_path: P,
+    ) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        Self::with_sink(
+            Sink::Broadcast(sender),
+            refresh_rate,
+            ListenerConfig::new(base_path.as_ref()),
+        )
+    }
+    /// Starts configuring a listener, an alternative to the constructors that
+    /// checks the settings given to it.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::event::{CatchUp, Event, EventListener};
+    /// # use tokio::sync::mpsc;
+    /// # #[tokio::main]
+    /// # async fn main() -> std::io::Result<()> {
+    /// let (tx, mut rx) = mpsc::channel::<Event>(16);
+    /// let listener = EventListener::builder()
+    ///     .refresh_rate(250)
+    ///     .database_path("./db")
+    ///     .catch_up(CatchUp::DropMissed)
+    ///     .build(tx)?;
+    /// tokio::spawn(listener.start());
+    ///
+    /// while let Some(event) = rx.recv().await {
+    ///     println!("{} just started", event.name);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn builder() -> EventListenerBuilder {
+        EventListenerBuilder::default()
+    }
+}
+impl EventListener<EventId> {
+    /// Creates a listener that only delivers the [`EventId`] of the events that fire,
+    /// for consumers that fetch the details themselves.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::{event::EventListener, Database};
+    /// # use tokio::sync::mpsc;
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let db = Database::new("./db").unwrap();
+    /// let (tx, mut rx) = mpsc::channel(16);
+    /// tokio::spawn(EventListener::new_id_only(tx, 500, "./db").start());
+    ///
+    /// while let Some(id) = rx.recv().await {
+    ///     if let Ok(Some(event)) = db.fetch_event(id) {
+    ///         println!("{} just started", event.name);
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn new_id_only<P>(sender: mpsc::Sender<EventId>, refresh_rate: u64, base_path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        Self::from_sender(sender, refresh_rate, base_path)
+    }
+}
+impl<T> EventListener<T>
+where
+    T: From<FiredEvent> + Send,
+{
+    /// Creates a listener just like [`new`](EventListener::new), but delivers any
+    /// type that can be made from a [`FiredEvent`] instead of an [`Event`].
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::event::{EventListener, FiredEvent};
+    /// # use tokio::sync::mpsc;
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let (tx, mut rx) = mpsc::channel::<FiredEvent>(16);
+    /// tokio::spawn(EventListener::from_sender(tx, 500, "./db").start());
+    /// # }
+    /// ```
+    pub fn from_sender<P>(sender: mpsc::Sender<T>, refresh_rate: u64, base_path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        Self::with_sink(
+            Sink::Mpsc(sender),
+            refresh_rate,
+            ListenerConfig::new(base_path.as_ref()),
+        )
+    }
+    fn with_sink(sink: Sink<T>, refresh_rate: u64, config: ListenerConfig) -> Self {
         Self {
+            sink,
+            refresh_rate: clamp_refresh_rate(refresh_rate),
+            shutdown: CancellationToken::new(),
+            stats: None,
+            config,
+        }
+    }
+    /// How often, in milliseconds, the listener checks for events.
+    pub fn refresh_rate(&self) -> u64 {
+        self.refresh_rate
+    }
+    /// Makes [`start`](EventListener::start) return once `token` is cancelled,
+    /// without it the listener runs for as long as the runtime does.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::event::EventListener;
+    /// # use tokio::sync::mpsc;
+    /// use tokio_util::sync::CancellationToken;
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let (tx, mut rx) = mpsc::channel(16);
+    /// let token = CancellationToken::new();
+    /// let listener = EventListener::new(tx, 500, "./db").with_cancellation(token.clone());
+    /// let handle = tokio::spawn(listener.start());
+    ///
+    /// // Later on, stop the listener and wait for it to finish
+    /// token.cancel();
+    /// handle.await.unwrap().unwrap();
+    /// # }
+    /// ```
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.shutdown = token;
+        self
+    }
+    /// Sets what to do with the events that should have fired before the listener
+    /// started, by default they are fired immediately.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::event::{CatchUp, EventListener, FiredEvent};
+    /// # use tokio::sync::mpsc;
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let (tx, mut rx) = mpsc::channel::<FiredEvent>(16);
+    /// let listener = EventListener::from_sender(tx, 500, "./db").with_catch_up(CatchUp::FireWithFlag);
+    /// tokio::spawn(listener.start());
+    ///
+    /// while let Some(fired) = rx.recv().await {
+    ///     match fired.late_by {
+    ///         Some(secs) if secs > 24 * 60 * 60 => println!("Too late for {}", fired.event.name),
+    ///         _ => println!("{} just started", fired.event.name),
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn with_catch_up(mut self, catch_up: CatchUp) -> Self {
+        self.config.catch_up = catch_up;
+        self
+    }
+    /// Makes the listener also read the event cache again every `ticks` checks for
+    /// events, instead of only when notified of a change.
+    ///
+    /// Changes are noticed through the filesystem notifications of the platform, like
+    /// inotify on Linux, which aren't delivered for changes made from other machines on
+    /// network filesystems (NFS, SMB) nor, depending on the setup, across container
+    /// volumes and some virtual machine shares. Polling is needed in those cases, and
+    /// with it the listener keeps running even if notifications are unavailable.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::event::EventListener;
+    /// # use tokio::sync::mpsc;
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let (tx, mut rx) = mpsc::channel(16);
+    /// // Checks for events every half a second and reads the cache every 5 seconds
+    /// let listener = EventListener::new(tx, 500, "/mnt/nfs/db").with_polling(10);
+    /// tokio::spawn(listener.start());
+    /// # }
+    /// ```
+    pub fn with_polling(mut self, ticks: u32) -> Self {
+        self.config.poll_every = Some(ticks.max(1));
+        self
+    }
+    /// Sets the file where the listener records the last time it checked for events,
+    /// by default `listener_state.ron` inside of the database folder.
+    ///
+    /// When starting, the listener reads it back to tell the events that came due while
+    /// it wasn't running, which [`CatchUp`] applies to, from the ones that came due
+    /// before it stopped and are still pending, which are delivered as usual. Without
+    /// the file every event that came due before starting counts as missed.
+    ///
+    /// It is a RON file holding the unix timestamp of the last check that found events
+    /// due, along with the queued events released from their
+    /// [`EventBuilder::depends_on`] and the event they depended on, like
+    /// `(last_tick: 1678000000, released: [(3, 1), (7, 1)])`. It is only rewritten
+    /// when either changes. Listeners of the same database should
+    /// each have their own.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::event::{CatchUp, EventListener};
+    /// # use tokio::sync::mpsc;
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let (tx, mut rx) = mpsc::channel(16);
+    /// let listener = EventListener::new(tx, 500, "./db")
+    ///     .with_catch_up(CatchUp::DropMissed)
+    ///     .with_state_file("./state/reminders.ron");
+    /// tokio::spawn(listener.start());
+    /// # }
+    /// ```
+    pub fn with_state_file<P>(mut self, path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        self.config.state_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+    /// Sets how the listener waits for the lock on the event cache, by default it
+    /// blocks until it is free, see [`LockMode`].
+    ///
+    /// The lock is waited for on tokio's blocking threads, through
+    /// [`spawn_blocking`](tokio::task::spawn_blocking), so the runtime's workers are
+    /// never held up by it. When retrying, a lock that isn't freed in time stops the
+    /// listener with [`NariError::LockTimeout`].
+    pub fn with_lock_mode(mut self, mode: LockMode) -> Self {
+        self.config.lock_mode = mode;
+        self
+    }
+    /// Sets the format the database is saved in, RON by default. It has to match the
+    /// one given to `Database::with_format`, see [`SerdeFormat`].
+    pub fn with_format(mut self, format: SerdeFormat) -> Self {
+        self.config.format = format;
+        self
+    }
+    /// Makes the listener also fire the events due within the next `tolerance_ms`
+    /// milliseconds on every check, none by default.
+    ///
+    /// The listener checks for events every `refresh_rate` milliseconds, so an event
+    /// due just after a check waits for almost a whole interval, and events due every
+    /// few seconds drift by up to that much. With a tolerance they fire up to
+    /// `tolerance_ms` milliseconds early instead, a tolerance as long as the refresh
+    /// rate never makes an event wait for another check.
+    pub fn with_tolerance(mut self, tolerance_ms: u64) -> Self {
+        self.config.tolerance_ms = tolerance_ms;
+        self
+    }
+    /// Sets how many folders the events are spread over, none by default. It has to
+    /// match the one given to `Database::with_shards`.
+    pub fn with_shards(mut self, shards: u64) -> Self {
+        self.config.shards = shards;
+        self
+    }
+    /// Sets what to do at the occurence of an event already delivered as a reminder,
+    /// by default it is delivered again, see [`LeadPolicy`].
+    pub fn with_lead_policy(mut self, policy: LeadPolicy) -> Self {
+        self.config.lead_policy = policy;
+        self
+    }
+    /// Makes the listener report how many events it delivered on every check through
+    /// `stats`, see [`ListenerStats`].
+    ///
+    /// Reports are dropped instead of slowing down the listener when the channel is
+    /// full, and nothing is reported without a stats channel.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::event::EventListener;
+    /// # use tokio::sync::mpsc;
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let (tx, mut rx) = mpsc::channel(16);
+    /// let (stats_tx, mut stats_rx) = mpsc::channel(64);
+    /// tokio::spawn(EventListener::new(tx, 500, "db").with_stats(stats_tx).start());
+    ///
+    /// # tokio::spawn(async move { while rx.recv().await.is_some() {} });
+    /// let mut total = 0;
+    /// while let Some(stats) = stats_rx.recv().await {
+    ///     total += stats.fired;
+    ///     println!("{total} events delivered as of {}", stats.tick_at);
+    /// }
+    /// # }
+    /// ```
+    pub fn with_stats(mut self, stats: mpsc::Sender<ListenerStats>) -> Self {
+        self.stats = Some(stats);
+        self
+    }
+    /// Runs the listener, delivering every event once its time comes until the
+    /// listener is cancelled.
+    ///
+    /// Fails if the event cache can't be read or watched when starting, or if the
+    /// cache or a rescheduled event can't be written afterwards. Events that can't be
+    /// read because they are being written by someone else are retried on the next tick.
+    ///
+    /// Events are delivered in the order of their occurences, and events happening at
+    /// the same second in the order they were added. Once nobody can receive them
+    /// anymore, because the receiver of the channel was dropped, the listener stops
+    /// with `Ok(())` the next time it has an event to deliver.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use std::time::{Duration, SystemTime, UNIX_EPOCH};
+    /// # use nari::models::{event::EventListener, Database, EventId};
+    /// # use tokio::sync::mpsc;
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_listener_start");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::new(&dir)?;
+    ///
+    /// // Starting with nothing queued is fine, events are picked up as they are added
+    /// let (tx, mut rx) = mpsc::channel(16);
+    /// tokio::spawn(EventListener::new(tx, 100, &dir).start());
+    /// # tokio::time::sleep(Duration::from_millis(200)).await;
+    /// # let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    /// db.build_event(EventId(1), "Tea time", now + 1).build().save_to_db(&db)?;
+    /// db.build_event(EventId(2), "Biscuits", now + 1).build().save_to_db(&db)?;
+    ///
+    /// for id in [EventId(1), EventId(2)] {
+    ///     let fired = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await;
+    ///     assert_eq!(fired.unwrap().unwrap().id, id);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Dropping the receiver ends the listener task without panicking:
+    /// ```
+    /// # use std::time::{Duration, SystemTime, UNIX_EPOCH};
+    /// # use nari::models::{event::EventListener, Database, EventId};
+    /// # use tokio::sync::mpsc;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let dir = std::env::temp_dir().join("nari_listener_dropped");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::new(&dir)?;
+    /// # let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    /// db.build_event(EventId(1), "Tea time", now + 1).build().save_to_db(&db)?;
+    ///
+    /// let (tx, rx) = mpsc::channel(16);
+    /// let handle = tokio::spawn(EventListener::for_database(tx, 100, &db).start());
+    /// drop(rx);
+    /// let stopped = tokio::time::timeout(Duration::from_secs(5), handle).await?;
+    /// assert!(stopped?.is_ok());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn start(self) -> Result<(), NariError> {
+        self.run_loop(|e| self.sink.send(e)).await
+    }
+    /// Runs the listener like [`start`](EventListener::start), but calls `f` with every
+    /// event instead of delivering it through the channel of the listener.
+    ///
+    /// The listener waits for `f` to return before going on, so a slow callback delays
+    /// the next check for events and every event after it. Use
+    /// [`EventListenerBuilder::build_detached`] to create a listener without a channel.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use std::time::{Duration, SystemTime, UNIX_EPOCH};
+    /// # use nari::models::{event::{Event, EventListener}, Database, EventId};
+    /// use tokio_util::sync::CancellationToken;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let dir = std::env::temp_dir().join("nari_run_with");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::new(&dir)?;
+    /// # let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    /// db.build_event(EventId(1), "Tea time", now + 1).build().save_to_db(&db)?;
+    ///
+    /// let token = CancellationToken::new();
+    /// let listener = EventListener::builder()
+    ///     .database(&db)
+    ///     .refresh_rate(100)
+    ///     .cancellation(token.clone())
+    ///     .build_detached::<Event>()?;
+    /// let mut names = vec![];
+    /// listener
+    ///     .run_with(|event| {
+    ///         names.push(event.name);
+    ///         token.cancel();
+    ///     })
+    ///     .await?;
+    /// assert_eq!(names, ["Tea time"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn run_with<F>(self, mut f: F) -> Result<(), NariError>
+    where
+        F: FnMut(T) + Send,
+    {
+        self.run_loop(|e| {
+            f(e);
+            future::ready(true)
+        })
+        .await
+    }
+    /// Runs the listener like [`run_with`](EventListener::run_with), but awaits the
+    /// future returned by `f` for every event.
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use nari::models::event::{Event, EventListener};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let listener = EventListener::builder()
+    ///     .database_path("./db")
+    ///     .build_detached::<Event>()?;
+    /// listener
+    ///     .run_with_async(|event| async move {
+    ///         println!("{} is starting", event.name);
+    ///     })
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn run_with_async<F, Fut>(self, mut f: F) -> Result<(), NariError>
+    where
+        F: FnMut(T) -> Fut + Send,
+        Fut: Future<Output = ()> + Send,
+    {
+        self.run_loop(|e| {
+            let delivered = f(e);
+            async move {
+                delivered.await;
+                true
+            }
+        })
+        .await
+    }
+    // Hands every event to `deliver`, which returns false if it can never be received
+    async fn run_loop<F, Fut>(&self, mut deliver: F) -> Result<(), NariError>
+    where
+        F: FnMut(T) -> Fut,
+        Fut: Future<Output = bool>,
+    {
+        let mut state = ListenerState::new(&self.config)?;
+        let mut interval = interval(Duration::from_millis(self.refresh_rate));
+        let mut fired: Vec<FiredEvent> = vec![];
+        loop {
+            // Waiting on a locked file blocks, so it must not hold up a runtime worker
+            let ticked = tokio::task::spawn_blocking(move || {
+                let tick_at = state.tick(&mut fired);
+                (state, fired, tick_at)
+            })
+            .await;
+            let tick_at;
+            (state, fired, tick_at) = match ticked {
+                Ok(ticked) => ticked,
+                Err(e) => std::panic::resume_unwind(e.into_panic()),
+            };
+            let tick_at = tick_at?;
+            let stats = ListenerStats {
+                fired: fired.len(),
+                tick_at: Timestamp(tick_at),
+            };
+            for e in fired.drain(..) {
+                // A full channel must not keep the listener from shutting down
+                let delivered = tokio::select! {
+                    delivered = deliver(T::from(e)) => delivered,
+                    _ = self.shutdown.cancelled() => return Ok(()),
+                };
+                if !delivered {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!("event channel closed, stopping the listener");
+                    return Ok(());
+                }
+            }
+            if let Some(sender) = &self.stats {
+                let _ = sender.try_send(stats);
+            }
+            tokio::select! {
+                _ = interval.tick() => (),
+                _ = self.shutdown.cancelled() => break,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How many events an [`EventStream`] holds before its listener waits for them to
+/// be taken.
+#[cfg(feature = "stream")]
+const STREAM_CAPACITY: usize = 16;
+
+/// The events of a listener as a [`Stream`](futures_core::Stream), in the order they
+/// fire, see [`EventListenerBuilder::build_stream`].
+///
+/// The listener runs for as long as the stream is kept, dropping it stops the listener.
+#[cfg(feature = "stream")]
+#[derive(Debug)]
+pub struct EventStream<T = Event> {
+    // Dropped first, so the listener is stopped before its channel is closed
+    _guard: tokio_util::sync::DropGuard,
+    receiver: mpsc::Receiver<T>,
+}
+#[cfg(feature = "stream")]
+impl<T> futures_core::Stream for EventStream<T> {
+    type Item = T;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<T>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+/// A listener just like [`EventListener`], but that runs on its own thread instead of
+/// a tokio runtime and delivers the events through a [`std::sync::mpsc`] channel.
+#[non_exhaustive]
+#[derive(Debug)]
+pub struct SyncEventListener {
+    sender: std_mpsc::Sender<Event>,
+    refresh_rate: u64,
+    shutdown: CancellationToken,
+    config: ListenerConfig,
+}
+impl SyncEventListener {
+    /// Creates a listener for the database found at `base_path`, that checks for events
+    /// every `refresh_rate` milliseconds, see [`EventListener::new`].
+    ///
+    /// ## Usage
+    /// ```no_run
+    /// # use std::time::{SystemTime, UNIX_EPOCH};
+    /// # use nari::models::{event::SyncEventListener, Database, EventId};
+    /// use std::sync::mpsc;
+    ///
+    /// let dir = std::env::temp_dir().join("nari_sync_listener");
+    /// let db = Database::new(&dir).unwrap();
+    /// # let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    /// db.build_event(EventId(1), "Tea time", now + 1).build().save_to_db(&db).unwrap();
+    ///
+    /// let (tx, rx) = mpsc::channel();
+    /// SyncEventListener::new(tx, 100, &dir).spawn();
+    /// assert_eq!(rx.recv().unwrap().id, EventId(1));
+    /// ```
+    pub fn new<P>(sender: std_mpsc::Sender<Event>, refresh_rate: u64, base_path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        Self::with_config(
             sender,
             refresh_rate,
+            ListenerConfig::new(base_path.as_ref()),
+        )
+    }
+    /// Creates a listener for `db`, see [`EventListener::for_database`].
+    pub fn for_database(sender: std_mpsc::Sender<Event>, refresh_rate: u64, db: &Database) -> Self {
+        Self::with_config(sender, refresh_rate, ListenerConfig::for_database(db))
+    }
+    fn with_config(
+        sender: std_mpsc::Sender<Event>,
+        refresh_rate: u64,
+        config: ListenerConfig,
+    ) -> Self {
+        Self {
+            sender,
+            refresh_rate: clamp_refresh_rate(refresh_rate),
+            shutdown: CancellationToken::new(),
+            config,
         }
     }
-    pub async fn start(self) {
-        // this may look dirty, cuz it is, please send help, i am not fit for this
-        let options = FileOptions::new().read(true).write(true).create(true);
-        let mut filelock = FileLock::lock("./db/event_cache.ron", true, options).unwrap();
-        let mut bytes = vec![];
-        filelock.file.read_to_end(&mut bytes).unwrap();
-        let event_cache: BTreeMap<u64, u64> = ron::de::from_bytes(&bytes).unwrap();
-        let event_cache = Arc::new(Mutex::new(event_cache));
-        let copy = Arc::clone(&event_cache);
-
-        let _watcher = tokio::spawn(async move {
-            let event_cache = Arc::clone(&event_cache);
-
-            let (tx, rx) = std::sync::mpsc::channel();
-            let mut w = RecommendedWatcher::new(tx, notify::Config::default()).unwrap();
-            w.watch(
-                Path::new("./db/event_cache.ron"),
-                notify::RecursiveMode::Recursive,
-            )
-            .unwrap();
-            while let Ok(f_ev) = rx.recv() {
-                if let Ok(file_event) = f_ev {
-                    match file_event.kind {
-                        EventKind::Modify(Data(_)) => {
-                            let options = FileOptions::new().read(true).write(true).create(true);
-                            let mut filelock =
-                                FileLock::lock("./db/event_cache.ron", true, options).unwrap();
-                            let mut bytes = vec![];
-                            filelock.file.read_to_end(&mut bytes).unwrap();
-                            let mut event_cache = event_cache.lock().unwrap();
-                            *event_cache =
-                                ron::de::from_bytes::<BTreeMap<u64, u64>>(&bytes).unwrap();
-                        }
-                        _ => (),
-                    }
+    /// Makes [`run`](SyncEventListener::run) return once `token` is cancelled, see
+    /// [`EventListener::with_cancellation`].
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.shutdown = token;
+        self
+    }
+    /// See [`EventListener::with_catch_up`].
+    pub fn with_catch_up(mut self, catch_up: CatchUp) -> Self {
+        self.config.catch_up = catch_up;
+        self
+    }
+    /// See [`EventListener::with_polling`].
+    pub fn with_polling(mut self, ticks: u32) -> Self {
+        self.config.poll_every = Some(ticks.max(1));
+        self
+    }
+    /// See [`EventListener::with_state_file`].
+    pub fn with_state_file<P>(mut self, path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        self.config.state_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+    /// See [`EventListener::with_lock_mode`].
+    pub fn with_lock_mode(mut self, mode: LockMode) -> Self {
+        self.config.lock_mode = mode;
+        self
+    }
+    /// See [`EventListener::with_tolerance`].
+    ///
+    /// ## Usage
+    /// ```
+    /// # use std::time::Duration;
+    /// # use nari::models::{event::SyncEventListener, Database, EventId, Timestamp};
+    /// # use tokio_util::sync::CancellationToken;
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_with_tolerance");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::new(&dir)?;
+    /// let now = Timestamp::now();
+    /// db.build_event(EventId(1), "Tea time", now + 60).build().save_to_db(&db)?;
+    /// db.build_event(EventId(2), "Dinner", now + 60 * 60).build().save_to_db(&db)?;
+    ///
+    /// // Anything due within two minutes fires right away
+    /// let (tx, rx) = std::sync::mpsc::channel();
+    /// let token = CancellationToken::new();
+    /// let handle = SyncEventListener::for_database(tx, 100, &db)
+    ///     .with_tolerance(2 * 60 * 1000)
+    ///     .with_cancellation(token.clone())
+    ///     .spawn();
+    /// assert_eq!(rx.recv_timeout(Duration::from_secs(5)).unwrap().id, EventId(1));
+    /// assert!(rx.recv_timeout(Duration::from_millis(500)).is_err());
+    /// token.cancel();
+    /// handle.join().unwrap()?;
+    /// # std::fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_tolerance(mut self, tolerance_ms: u64) -> Self {
+        self.config.tolerance_ms = tolerance_ms;
+        self
+    }
+    /// See [`EventListener::with_shards`].
+    pub fn with_shards(mut self, shards: u64) -> Self {
+        self.config.shards = shards;
+        self
+    }
+    /// See [`EventListener::with_lead_policy`].
+    pub fn with_lead_policy(mut self, policy: LeadPolicy) -> Self {
+        self.config.lead_policy = policy;
+        self
+    }
+    /// See [`EventListener::with_format`].
+    pub fn with_format(mut self, format: SerdeFormat) -> Self {
+        self.config.format = format;
+        self
+    }
+    /// Runs the listener on the current thread, blocking it until the listener is
+    /// cancelled or the receiving half of the channel is found dropped, which only
+    /// happens when an event is delivered.
+    ///
+    /// Fails in the same cases as [`EventListener::start`].
+    ///
+    /// Like [`EventListener`], it keeps following the event cache when the file is
+    /// deleted and created again or replaced by renaming another file over it, as
+    /// the folder holding the cache is watched rather than the file itself.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use std::time::{Duration, SystemTime, UNIX_EPOCH};
+    /// # use nari::models::{event::SyncEventListener, Database, EventId};
+    /// # use tokio_util::sync::CancellationToken;
+    /// # fn main() -> std::io::Result<()> {
+    /// let dir = std::env::temp_dir().join("nari_sync_listener_run");
+    /// let other_dir = std::env::temp_dir().join("nari_sync_listener_run_other");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// # let _ = std::fs::remove_dir_all(&other_dir);
+    /// let db = Database::new(&dir)?;
+    /// let (tx, rx) = std::sync::mpsc::channel();
+    /// let token = CancellationToken::new();
+    /// let handle = SyncEventListener::new(tx, 50, &dir)
+    ///     .with_cancellation(token.clone())
+    ///     .spawn();
+    /// # std::thread::sleep(Duration::from_millis(200));
+    ///
+    /// // Replace the cache with one made somewhere else
+    /// # let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    /// let other = Database::new(&other_dir)?;
+    /// other.build_event(EventId(1), "Tea time", now + 1).build().save_to_db(&other)?;
+    /// std::fs::copy(other_dir.join("events/1.ron"), dir.join("events/1.ron"))?;
+    /// std::fs::remove_file(db.cache_path())?;
+    /// std::fs::rename(other.cache_path(), db.cache_path())?;
+    ///
+    /// let fired = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    /// assert_eq!(fired.id, EventId(1));
+    /// token.cancel();
+    /// handle.join().unwrap()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn run(self) -> Result<(), NariError> {
+        let mut state = ListenerState::new(&self.config)?;
+        let mut fired: Vec<FiredEvent> = vec![];
+        while !self.shutdown.is_cancelled() {
+            state.tick(&mut fired)?;
+            for e in fired.drain(..) {
+                if self.sender.send(e.event).is_err() {
+                    return Ok(());
                 }
             }
-        });
-        let mut interval = interval(Duration::from_millis(self.refresh_rate));
-        let mut ids: Vec<u64> = vec![];
-        loop {
-            let now = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs();
-            if Self::has_passed_event(now, &copy.lock().unwrap()) {
-                let mut lock = copy.lock().unwrap();
-                for (_, id) in lock.range(..now) {
-                    ids.push(*id);
+            std::thread::sleep(Duration::from_millis(self.refresh_rate));
+        }
+        Ok(())
+    }
+    /// Runs the listener on a new thread, see [`run`](SyncEventListener::run).
+    pub fn spawn(self) -> JoinHandle<Result<(), NariError>> {
+        std::thread::spawn(move || self.run())
+    }
+}
+
+/// Default name of the file where listeners record their last check.
+const STATE_FILE_NAME: &str = "listener_state.ron";
+
+/// What a listener saves in its state file, see [`EventListener::with_state_file`].
+#[derive(Debug, Serialize, Deserialize)]
+struct Checkpoint {
+    last_tick: u64,
+    #[serde(default)]
+    released: BTreeSet<(u64, u64)>,
+}
+
+/// Keeps track of the event cache and finds the events to fire, for both
+/// [`EventListener`] and [`SyncEventListener`].
+struct ListenerState {
+    base_path: PathBuf,
+    cache_path: PathBuf,
+    cache: Arc<Mutex<EventQueue>>,
+    // Runs on its own thread for as long as the state is kept
+    _watcher: Option<RecommendedWatcher>,
+    catch_up: CatchUp,
+    poll_every: Option<u32>,
+    ticks_until_poll: u32,
+    started_at: u64,
+    state_path: PathBuf,
+    // Last check of the previous run, events due before it aren't missed
+    previous_tick: u64,
+    saved_tick: u64,
+    // What the queued events depend on by their place in the queue, read only once
+    dependencies: BTreeMap<(u64, u64), Option<u64>>,
+    // Queued events along with the dependency they were released from
+    released: BTreeSet<(u64, u64)>,
+    lock_mode: LockMode,
+    format: SerdeFormat,
+    shards: u64,
+    layout: Layout,
+    // Events due up to this much after a check fire on it
+    tolerance: Duration,
+    lead_policy: LeadPolicy,
+    // Files are written in the same encoding as the cache was found in
+    codec: Codec,
+    ids: Vec<(u64, u64)>,
+    handled: Vec<(u64, u64)>,
+    queued: Vec<(u64, u64)>,
+}
+impl ListenerState {
+    fn new(config: &ListenerConfig) -> Result<Self, NariError> {
+        let ListenerConfig {
+            ref base_path,
+            ref cache_path,
+            ref state_path,
+            catch_up,
+            poll_every,
+            lock_mode,
+            format,
+            codec,
+            layout,
+            shards,
+            tolerance_ms,
+            lead_policy,
+        } = *config;
+        let cache_path = match cache_path {
+            Some(path) => path.clone(),
+            None => base_path.join(cache_file_name(format)),
+        };
+        let state_path = match state_path {
+            Some(path) => path.clone(),
+            None => base_path.join(STATE_FILE_NAME),
+        };
+        let (event_cache, found): (EventQueue, _) =
+            read_path_with_codec(base_path, &cache_path, format, codec)?;
+        // Encrypted databases are never written back in plain text
+        let codec = match codec {
+            #[cfg(feature = "encryption")]
+            Codec::Encrypted(_) => codec,
+            _ => found,
+        };
+        let cache = Arc::new(Mutex::new(event_cache));
+
+        // Without notifications polling still keeps the listener up to date
+        let watcher =
+            match Self::watch_cache(base_path, &cache_path, format, codec, Arc::clone(&cache)) {
+                Ok(watcher) => Some(watcher),
+                Err(_) if poll_every.is_some() => None,
+                Err(e) => return Err(e),
+            };
+        let started_at = Timestamp::now().0;
+        // The state is only a hint, without it every due event counts as missed
+        let (previous_tick, released) =
+            match read_path::<Checkpoint>(base_path, &state_path, SerdeFormat::Ron, Codec::Plain) {
+                Ok(c) => (c.last_tick, c.released),
+                Err(_) => (0, BTreeSet::new()),
+            };
+        Ok(Self {
+            base_path: base_path.to_path_buf(),
+            cache_path,
+            cache,
+            _watcher: watcher,
+            catch_up,
+            poll_every,
+            ticks_until_poll: 0,
+            started_at,
+            state_path,
+            previous_tick,
+            saved_tick: 0,
+            dependencies: BTreeMap::new(),
+            released,
+            lock_mode,
+            format,
+            shards,
+            layout,
+            tolerance: Duration::from_millis(tolerance_ms),
+            lead_policy,
+            codec,
+            ids: vec![],
+            handled: vec![],
+            queued: vec![],
+        })
+    }
+    /// Takes the events whose time has come out of the cache, rescheduling the
+    /// repeating ones, and adds the ones to deliver to `fired`.
+    ///
+    /// Returns the unix timestamp the events were checked at.
+    fn tick(&mut self, fired: &mut Vec<FiredEvent>) -> Result<u64, NariError> {
+        if let Some(poll_every) = self.poll_every {
+            if self.ticks_until_poll == 0 {
+                // A cache caught halfway through a write is read on the next poll
+                if let Ok(tree) = read_path::<EventQueue>(
+                    &self.base_path,
+                    &self.cache_path,
+                    self.format,
+                    self.codec,
+                ) {
+                    *self.cache.lock().unwrap() = tree;
                 }
-                lock.retain(|k, _| *k >= now);
-                let writer = File::create("./db/event_cache.ron").unwrap();
-                ron::ser::to_writer(writer, &*lock).unwrap();
-                drop(lock);
-            }
-            if !ids.is_empty() {
-                for id in &ids {
-                    let buf = BufReader::new(File::open(format!("./db/events/{id}.ron")).unwrap());
-                    let e: Event = ron::de::from_reader(buf).unwrap();
-                    self.sender.send(e.clone()).await.unwrap();
+                self.ticks_until_poll = poll_every;
+            }
+            self.ticks_until_poll -= 1;
+        }
+        // Read once, as the tolerance needs the milliseconds that `Timestamp` drops
+        let clock = SystemTime::now();
+        let now = Timestamp::from_system_time(clock).0;
+        let due = Timestamp::from_system_time(clock + self.tolerance).0;
+        if !Self::has_passed_event(due, &self.cache.lock().unwrap()) {
+            return Ok(now);
+        }
+        let cache = Arc::clone(&self.cache);
+        let mut lock = cache.lock().unwrap();
+        self.ids.extend(lock.range(..=due));
+        // Taken out for the loop, as reading and writing the events borrows the state
+        let mut ids = std::mem::take(&mut self.ids);
+        let released_before = self.released.clone();
+        // Saved once an event comes due, so it isn't counted as missed on the next run
+        let mut newly_due = false;
+        for (k, id) in ids.drain(..) {
+            newly_due |= k > self.saved_tick;
+            let mut e = match self.read_event(id) {
+                Ok(e) => e,
+                // The event was deleted in the meantime
+                Err(err) if err.is_not_found() => {
+                    self.handled.push((k, id));
+                    continue;
                 }
-                ids.clear();
+                // Left in the queue to be retried on the next tick
+                Err(_) => continue,
+            };
+            // Left in the queue until its dependency fires
+            if self.is_held_back(&e) {
+                continue;
             }
-            interval.tick().await;
+            self.handled.push((k, id));
+            // Paused after the cache was last read
+            if !e.enabled {
+                continue;
+            }
+            #[cfg(feature = "tracing")]
+            tracing::debug!(id, at = k, "event fired");
+            let is_reminder = e.lead_secs.is_some() && k < e.fires_at();
+            if is_reminder && self.lead_policy == LeadPolicy::RemindAndFire {
+                // Fires again at the occurence itself, which is when it counts
+                self.queued.push((e.fires_at(), id));
+            } else {
+                e.occurrences_fired = e.occurrences_fired.saturating_add(1);
+                self.release_dependents(id, &lock);
+                let next = match e.remaining_occurrences() {
+                    Some(0) => None,
+                    _ => Self::next_occurence_after(&e, now),
+                };
+                if let Some(next) = next {
+                    let mut rescheduled = e.clone();
+                    rescheduled.move_to(next);
+                    self.write_event(&rescheduled)?;
+                    self.queued
+                        .extend(rescheduled.queued_at().map(|at| (at, id)));
+                } else {
+                    self.write_event(&e)?;
+                }
+            }
+            let late_by = match self.catch_up {
+                _ if k >= self.started_at || k <= self.previous_tick => None,
+                CatchUp::DropMissed => continue,
+                CatchUp::FireImmediately => None,
+                CatchUp::FireWithFlag => Some(now - k),
+            };
+            fired.push(FiredEvent {
+                event: e,
+                late_by,
+                is_reminder,
+            });
+        }
+        self.ids = ids;
+        // Events held back or that couldn't be read leave the cache as it is
+        if self.handled.is_empty() && self.queued.is_empty() {
+            drop(lock);
+            if newly_due {
+                self.write_checkpoint(now)?;
+            }
+            return Ok(now);
+        }
+        // The changes are applied to the cache as it is now, so that events
+        // added since it was last read aren't lost
+        let cache_lock = lock_cache(&self.cache_path, self.lock_mode)?;
+        let mut tree: EventQueue =
+            read_path(&self.base_path, &self.cache_path, self.format, self.codec)?;
+        for (k, id) in self.handled.drain(..) {
+            tree.remove(k, id);
         }
+        tree.extend(self.queued.drain(..));
+        write_atomically(
+            &self.base_path,
+            &self.cache_path,
+            &tree,
+            self.format,
+            self.codec,
+        )?;
+        drop(cache_lock);
+        // Events stay released for as long as they are queued
+        if !self.released.is_empty() {
+            let queued: HashSet<u64> = tree.range(..).map(|(_, id)| id).collect();
+            self.released
+                .retain(|(dependent, _)| queued.contains(dependent));
+        }
+        *lock = tree;
+        drop(lock);
+        // Saved right away, as the events depending on them may fire next
+        if newly_due || self.released != released_before {
+            self.write_checkpoint(now)?;
+        }
+        Ok(now)
     }
-    fn has_passed_event(now: u64, events: &BTreeMap<u64, u64>) -> bool {
-        if let Some((k, _)) = events.first_key_value() {
-            *k <= now
-        } else {
-            false
+    fn read_event(&self, id: u64) -> Result<Event, NariError> {
+        match self.layout {
+            Layout::Files => {
+                let path = entry_path(&self.base_path, "events", id, self.format, self.shards);
+                read_versioned(&self.base_path, &path, self.format, self.codec)
+            }
+            Layout::Consolidated => {
+                let path = consolidated_path(&self.base_path, "events", self.format);
+                read_versioned_all(&self.base_path, &path, self.format, self.codec)?
+                    .remove(&id)
+                    .ok_or_else(|| NariError::NotFound(format!("event {id}")))
+            }
+        }
+    }
+    fn write_event(&self, e: &Event) -> Result<(), NariError> {
+        match self.layout {
+            Layout::Files => {
+                let path = entry_path(&self.base_path, "events", e.id.0, self.format, self.shards);
+                write_atomically(&self.base_path, &path, e, self.format, self.codec)
+            }
+            Layout::Consolidated => update_consolidated(
+                &self.base_path,
+                &consolidated_path(&self.base_path, "events", self.format),
+                self.lock_mode,
+                self.format,
+                self.codec,
+                None,
+                |entries: &mut BTreeMap<u64, Event>| {
+                    entries.insert(e.id.0, e.clone());
+                },
+            ),
+        }
+    }
+    // Whether `e` depends on an event that still exists and hasn't fired yet
+    fn is_held_back(&self, e: &Event) -> bool {
+        match e.depends_on {
+            Some(dep) if !self.released.contains(&(e.id.0, dep.0)) => {
+                !matches!(self.read_event(dep.0), Err(err) if err.is_not_found())
+            }
+            _ => false,
+        }
+    }
+    // Releases the events in `queue` that depend on `id`, reading the ones queued
+    // since the last time to tell what they depend on
+    fn release_dependents(&mut self, id: u64, queue: &EventQueue) {
+        let mut known = std::mem::take(&mut self.dependencies);
+        let mut unknown = vec![];
+        for entry in queue.range(..) {
+            match known.remove(&entry) {
+                Some(dep) => {
+                    self.dependencies.insert(entry, dep);
+                }
+                None => unknown.push(entry),
+            }
+        }
+        let read = self.read_dependencies(unknown);
+        self.dependencies.extend(read);
+        let waiting = self
+            .dependencies
+            .iter()
+            .filter(|(_, dep)| **dep == Some(id))
+            .map(|((_, dependent), _)| (*dependent, id));
+        self.released.extend(waiting);
+    }
+    // What the events at `entries` depend on, leaving out the ones that can't be read
+    // to be read again the next time
+    fn read_dependencies(&self, entries: Vec<(u64, u64)>) -> Vec<((u64, u64), Option<u64>)> {
+        let all = match self.layout {
+            Layout::Files => None,
+            // Read once for every event instead of once per event
+            Layout::Consolidated => {
+                let path = consolidated_path(&self.base_path, "events", self.format);
+                match read_versioned_all::<Event>(&self.base_path, &path, self.format, self.codec) {
+                    Ok(all) => Some(all),
+                    Err(_) => return vec![],
+                }
+            }
+        };
+        entries
+            .into_iter()
+            .filter_map(|(k, id)| {
+                let dep = match &all {
+                    Some(all) => all.get(&id).and_then(|e| e.depends_on),
+                    None => match self.read_event(id) {
+                        Ok(e) => e.depends_on,
+                        Err(err) if err.is_not_found() => None,
+                        Err(_) => return None,
+                    },
+                };
+                Some(((k, id), dep.map(|d| d.0)))
+            })
+            .collect()
+    }
+    fn write_checkpoint(&mut self, now: u64) -> Result<(), NariError> {
+        let checkpoint = Checkpoint {
+            last_tick: now,
+            released: self.released.clone(),
+        };
+        write_atomically(
+            &self.base_path,
+            &self.state_path,
+            &checkpoint,
+            SerdeFormat::Ron,
+            Codec::Plain,
+        )?;
+        self.saved_tick = now;
+        Ok(())
+    }
+    // The cache is replaced on every write, so its folder is watched rather than the
+    // file itself. The watcher runs on its own thread until it is dropped at shutdown
+    fn watch_cache(
+        base_path: &Path,
+        cache_path: &Path,
+        format: SerdeFormat,
+        codec: Codec,
+        event_cache: Arc<Mutex<EventQueue>>,
+    ) -> Result<RecommendedWatcher, NariError> {
+        let watched_path = cache_path.to_path_buf();
+        let watched_base = base_path.to_path_buf();
+        let mut watcher = RecommendedWatcher::new(
+            move |f_ev: notify::Result<notify::Event>| {
+                if let Ok(file_event) = f_ev {
+                    // Paths are reported in absolute form, so only the names are compared
+                    let is_cache = file_event
+                        .paths
+                        .iter()
+                        .any(|p| p.file_name() == watched_path.file_name());
+                    if is_cache
+                        && matches!(file_event.kind, EventKind::Create(_) | EventKind::Modify(_))
+                    {
+                        if let Ok(tree) =
+                            read_path::<EventQueue>(&watched_base, &watched_path, format, codec)
+                        {
+                            *event_cache.lock().unwrap() = tree;
+                        }
+                    }
+                }
+            },
+            notify::Config::default(),
+        )
+        .map_err(|e| NariError::Io(io::Error::other(e)))?;
+        watcher
+            .watch(base_path, notify::RecursiveMode::NonRecursive)
+            .map_err(|e| NariError::Io(io::Error::other(e)))?;
+        Ok(watcher)
+    }
+    // Repeating events are moved to their first occurence after `now`, so any
+    // repetitions missed in between are only fired once
+    fn next_occurence_after(event: &Event, now: u64) -> Option<Timestamp> {
+        let mut next = event.next_repetition(event.next_occurence)?;
+        while next < now {
+            next = event.next_repetition(next)?;
+        }
+        Some(next)
+    }
+    fn has_passed_event(now: u64, events: &EventQueue) -> bool {
+        events.first_at().is_some_and(|at| at <= now)
+    }
+}
+
+/// Configures an [`EventListener`] step by step, see [`EventListener::builder`].
+#[derive(Debug, Clone)]
+pub struct EventListenerBuilder {
+    refresh_rate: u64,
+    shutdown: CancellationToken,
+    stats: Option<mpsc::Sender<ListenerStats>>,
+    config: ListenerConfig,
+}
+impl Default for EventListenerBuilder {
+    fn default() -> Self {
+        Self {
+            refresh_rate: 500,
+            shutdown: CancellationToken::new(),
+            stats: None,
+            config: ListenerConfig::new(Path::new("db")),
+        }
+    }
+}
+impl EventListenerBuilder {
+    /// Sets how often, in milliseconds, the listener checks for events, 500 by default.
+    /// Unlike with the constructors, zero makes [`build`](EventListenerBuilder::build) fail.
+    pub fn refresh_rate(mut self, refresh_rate: u64) -> Self {
+        self.refresh_rate = refresh_rate;
+        self
+    }
+    /// Sets the path of the database to listen to, the same path given to
+    /// [`Database::new`], `db` by default.
+    pub fn database_path<P>(mut self, path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        self.config.base_path = path.as_ref().to_path_buf();
+        self.config.cache_path = None;
+        self
+    }
+    /// Listens to `db`, taking the location of its files, their format and how to
+    /// wait for its locks from it, see [`EventListener::for_database`].
+    pub fn database(mut self, db: &Database) -> Self {
+        self.config.set_database(db);
+        self
+    }
+    /// See [`EventListener::with_cancellation`].
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.shutdown = token;
+        self
+    }
+    /// See [`EventListener::with_catch_up`].
+    pub fn catch_up(mut self, catch_up: CatchUp) -> Self {
+        self.config.catch_up = catch_up;
+        self
+    }
+    /// See [`EventListener::with_polling`].
+    pub fn poll_every(mut self, ticks: u32) -> Self {
+        self.config.poll_every = Some(ticks.max(1));
+        self
+    }
+    /// See [`EventListener::with_state_file`].
+    pub fn state_path<P>(mut self, path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        self.config.state_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+    /// See [`EventListener::with_lock_mode`].
+    pub fn lock_mode(mut self, mode: LockMode) -> Self {
+        self.config.lock_mode = mode;
+        self
+    }
+    /// See [`EventListener::with_format`].
+    pub fn format(mut self, format: SerdeFormat) -> Self {
+        self.config.format = format;
+        self
+    }
+    /// See [`EventListener::with_tolerance`].
+    pub fn tolerance(mut self, tolerance_ms: u64) -> Self {
+        self.config.tolerance_ms = tolerance_ms;
+        self
+    }
+    /// See [`EventListener::with_shards`].
+    pub fn shards(mut self, shards: u64) -> Self {
+        self.config.shards = shards;
+        self
+    }
+    /// See [`EventListener::with_lead_policy`].
+    pub fn lead_policy(mut self, policy: LeadPolicy) -> Self {
+        self.config.lead_policy = policy;
+        self
+    }
+    /// See [`EventListener::with_stats`].
+    pub fn stats(mut self, stats: mpsc::Sender<ListenerStats>) -> Self {
+        self.stats = Some(stats);
+        self
+    }
+    /// Creates a listener delivering to `sender`, which can be of any type
+    /// accepted by [`EventListener::from_sender`].
+    ///
+    /// Fails with [`NariError::InvalidInput`] if the refresh rate is zero.
+    pub fn build<T>(self, sender: mpsc::Sender<T>) -> Result<EventListener<T>, NariError>
+    where
+        T: From<FiredEvent> + Send,
+    {
+        self.build_with_sink(Sink::Mpsc(sender))
+    }
+    /// Creates a listener delivering to every subscriber of `sender`, see
+    /// [`EventListener::new_broadcast`] for how lagging subscribers are handled.
+    ///
+    /// Fails with [`NariError::InvalidInput`] if the refresh rate is zero.
+    pub fn build_broadcast<T>(
+        self,
+        sender: broadcast::Sender<T>,
+    ) -> Result<EventListener<T>, NariError>
+    where
+        T: From<FiredEvent> + Send,
+    {
+        self.build_with_sink(Sink::Broadcast(sender))
+    }
+    /// Starts a listener on the current tokio runtime and returns its events as an
+    /// [`EventStream`], only available with the `stream` feature.
+    ///
+    /// Dropping the stream stops the listener, and the stream ends if the listener
+    /// stops on its own, either because of an error or because its
+    /// [`cancellation`](EventListenerBuilder::cancellation) token was cancelled.
+    ///
+    /// Fails with [`NariError::InvalidInput`] if the refresh rate is zero.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use std::time::{Duration, SystemTime, UNIX_EPOCH};
+    /// # use nari::models::{event::{Event, EventListener}, Database, EventId};
+    /// use futures_util::StreamExt;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let dir = std::env::temp_dir().join("nari_build_stream");
+    /// # let _ = std::fs::remove_dir_all(&dir);
+    /// let db = Database::new(&dir)?;
+    /// let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    /// for (id, name) in [(1, "Tea time"), (2, "Nap"), (3, "Biscuits")] {
+    ///     db.build_event(EventId(id), name, now + 1).build().save_to_db(&db)?;
+    /// }
+    ///
+    /// let events = EventListener::builder()
+    ///     .database(&db)
+    ///     .refresh_rate(100)
+    ///     .build_stream::<Event>()?;
+    /// let snacks: Vec<Event> = events
+    ///     .filter(|e| std::future::ready(e.name != "Nap"))
+    ///     .take(2)
+    ///     .collect()
+    ///     .await;
+    /// assert_eq!(snacks.len(), 2);
+    /// # std::fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "stream")]
+    pub fn build_stream<T>(mut self) -> Result<EventStream<T>, NariError>
+    where
+        T: From<FiredEvent> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel(STREAM_CAPACITY);
+        let token = self.shutdown.child_token();
+        self.shutdown = token.clone();
+        let listener = self.build_with_sink(Sink::Mpsc(sender))?;
+        tokio::spawn(listener.start());
+        Ok(EventStream {
+            _guard: token.drop_guard(),
+            receiver,
+        })
+    }
+    /// Creates a listener that doesn't deliver to any channel, to be run with
+    /// [`EventListener::run_with`] or [`EventListener::run_with_async`]. Events are
+    /// dropped when running it with [`start`](EventListener::start).
+    ///
+    /// Fails with [`NariError::InvalidInput`] if the refresh rate is zero.
+    pub fn build_detached<T>(self) -> Result<EventListener<T>, NariError>
+    where
+        T: From<FiredEvent> + Send,
+    {
+        self.build_with_sink(Sink::Detached)
+    }
+    fn build_with_sink<T>(self, sink: Sink<T>) -> Result<EventListener<T>, NariError>
+    where
+        T: From<FiredEvent> + Send,
+    {
+        if self.refresh_rate == 0 {
+            return Err(NariError::InvalidInput(String::from(
+                "the refresh rate of an event listener can't be zero",
+            )));
         }
+        let mut listener = EventListener::with_sink(sink, self.refresh_rate, self.config)
+            .with_cancellation(self.shutdown);
+        listener.stats = self.stats;
+        Ok(listener)
     }
 }
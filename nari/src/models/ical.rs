@@ -0,0 +1,145 @@
+use std::{
+    io::{self, Write},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::{
+    calendar::{civil_from_days, SECS_PER_DAY},
+    event::{Event, Repeatability},
+};
+
+/// Longest a content line can be, in octets, before it has to be folded.
+const MAX_LINE_LENGTH: usize = 75;
+
+/// Writes `events` as an iCalendar (RFC 5545) object with one VEVENT per event.
+pub(crate) fn write_calendar<W>(mut writer: W, events: &[Event]) -> io::Result<()>
+where
+    W: Write,
+{
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+
+    write_line(&mut writer, "BEGIN:VCALENDAR")?;
+    write_line(&mut writer, "VERSION:2.0")?;
+    write_line(&mut writer, "PRODID:-//Nari//Nari//EN")?;
+    for event in events {
+        write_line(&mut writer, "BEGIN:VEVENT")?;
+        write_line(&mut writer, &format!("UID:{}@nari", event.id))?;
+        write_line(&mut writer, &format!("DTSTAMP:{}", date_time(now)))?;
+        if event.all_day {
+            write_line(
+                &mut writer,
+                &format!("DTSTART;VALUE=DATE:{}", date(event.next_occurence.0)),
+            )?;
+            if let Some(end) = event.end {
+                write_line(&mut writer, &format!("DTEND;VALUE=DATE:{}", date(end.0)))?;
+            }
+        } else {
+            write_line(
+                &mut writer,
+                &format!("DTSTART:{}", date_time(event.next_occurence.0)),
+            )?;
+            if let Some(end) = event.end {
+                write_line(&mut writer, &format!("DTEND:{}", date_time(end.0)))?;
+            }
+        }
+        write_line(&mut writer, &format!("SUMMARY:{}", escape(&event.name)))?;
+        if !event.description.is_empty() {
+            write_line(
+                &mut writer,
+                &format!("DESCRIPTION:{}", escape(&event.description)),
+            )?;
+        }
+        if !event.tags.is_empty() {
+            let mut tags = event.tags.iter().map(|t| escape(t)).collect::<Vec<_>>();
+            tags.sort();
+            write_line(&mut writer, &format!("CATEGORIES:{}", tags.join(",")))?;
+        }
+        if let Some(rule) = recurrence_rule(&event.repeats) {
+            match event.remaining_occurrences() {
+                // Finished events only keep their last occurence
+                Some(0) => (),
+                Some(count) => write_line(&mut writer, &format!("RRULE:{rule};COUNT={count}"))?,
+                None => write_line(&mut writer, &format!("RRULE:{rule}"))?,
+            }
+        }
+        write_line(&mut writer, "END:VEVENT")?;
+    }
+    write_line(&mut writer, "END:VCALENDAR")
+}
+
+fn recurrence_rule(repeats: &Repeatability) -> Option<String> {
+    let rule = match repeats {
+        Repeatability::Yearly => "FREQ=YEARLY",
+        Repeatability::Biyearly => "FREQ=YEARLY;INTERVAL=2",
+        Repeatability::Quarterly => "FREQ=MONTHLY;INTERVAL=3",
+        Repeatability::Monthly => "FREQ=MONTHLY",
+        Repeatability::Bimonthly => "FREQ=MONTHLY;INTERVAL=2",
+        Repeatability::Weekly => "FREQ=WEEKLY",
+        Repeatability::Daily => "FREQ=DAILY",
+        Repeatability::Hourly => "FREQ=HOURLY",
+        Repeatability::Custom { interval_secs: 0 } | Repeatability::Never => return None,
+        Repeatability::Custom { interval_secs } => {
+            // Use the biggest unit the interval is made of
+            let (freq, unit) = [("DAILY", SECS_PER_DAY), ("HOURLY", 3600), ("MINUTELY", 60)]
+                .into_iter()
+                .find(|(_, unit)| interval_secs % unit == 0)
+                .unwrap_or(("SECONDLY", 1));
+            return Some(format!("FREQ={freq};INTERVAL={}", interval_secs / unit));
+        }
+    };
+    Some(String::from(rule))
+}
+
+/// Formats the UTC day of a unix timestamp as a DATE value.
+fn date(timestamp: u64) -> String {
+    let (year, month, day) = civil_from_days((timestamp / SECS_PER_DAY) as i64);
+    format!("{year:04}{month:02}{day:02}")
+}
+/// Formats a unix timestamp as an UTC DATE-TIME value.
+fn date_time(timestamp: u64) -> String {
+    let (year, month, day) = civil_from_days((timestamp / SECS_PER_DAY) as i64);
+    let secs = timestamp % SECS_PER_DAY;
+    format!(
+        "{year:04}{month:02}{day:02}T{:02}{:02}{:02}Z",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// Escapes a TEXT value.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => (),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Writes a content line, folding it so that no line is longer than 75 octets
+/// without splitting any character.
+fn write_line<W>(writer: &mut W, line: &str) -> io::Result<()>
+where
+    W: Write,
+{
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > MAX_LINE_LENGTH {
+            writer.write_all(b"\r\n ")?;
+            length = 1;
+        }
+        let mut buf = [0; 4];
+        writer.write_all(c.encode_utf8(&mut buf).as_bytes())?;
+        length += c.len_utf8();
+    }
+    writer.write_all(b"\r\n")
+}
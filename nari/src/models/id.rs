@@ -2,6 +2,16 @@ use std::fmt::Display;
 
 use serde::{Deserialize, Serialize};
 
+/// Identifies a [`User`](super::User), it converts from and into a plain `u64`.
+///
+/// ## Usage
+/// ```
+/// # use nari::models::UserId;
+/// let id: UserId = 42.into();
+/// assert_eq!(id, UserId(42));
+/// let raw: u64 = id.into();
+/// assert_eq!(raw, 42);
+/// ```
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct UserId(pub u64);
 
@@ -10,6 +20,26 @@ impl Display for UserId {
         write!(f, "{}", self.0)
     }
 }
+impl From<u64> for UserId {
+    fn from(id: u64) -> Self {
+        Self(id)
+    }
+}
+impl From<UserId> for u64 {
+    fn from(id: UserId) -> Self {
+        id.0
+    }
+}
+/// Identifies an [`Event`](super::event::Event), it converts from and into a plain `u64`.
+///
+/// ## Usage
+/// ```
+/// # use nari::models::EventId;
+/// let id: EventId = 42.into();
+/// assert_eq!(id, EventId(42));
+/// let raw: u64 = id.into();
+/// assert_eq!(raw, 42);
+/// ```
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct EventId(pub u64);
 
@@ -18,3 +48,13 @@ impl Display for EventId {
         write!(f, "{}", self.0)
     }
 }
+impl From<u64> for EventId {
+    fn from(id: u64) -> Self {
+        Self(id)
+    }
+}
+impl From<EventId> for u64 {
+    fn from(id: EventId) -> Self {
+        id.0
+    }
+}
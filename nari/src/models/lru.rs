@@ -0,0 +1,67 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::SystemTime,
+};
+
+use super::event::Event;
+
+/// The events fetched last, along with when the file they were read from was last
+/// modified, see [`Database::with_event_cache`](super::Database::with_event_cache).
+///
+/// Once full, the event used the longest ago makes room for the new one.
+#[derive(Debug)]
+pub(super) struct EventLru {
+    capacity: usize,
+    // The event, when its file was modified and when it was last used
+    entries: HashMap<u64, (Event, SystemTime, u64)>,
+    // The ids by when they were last used, the oldest first
+    by_use: BTreeMap<u64, u64>,
+    uses: u64,
+}
+impl EventLru {
+    pub(super) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            by_use: BTreeMap::new(),
+            uses: 0,
+        }
+    }
+    /// Returns the event `id` if it was read from its file as last modified at
+    /// `modified`.
+    pub(super) fn get(&mut self, id: u64, modified: SystemTime) -> Option<Event> {
+        let (event, at, used) = self.entries.get_mut(&id)?;
+        if *at != modified {
+            return None;
+        }
+        self.by_use.remove(used);
+        self.uses += 1;
+        *used = self.uses;
+        self.by_use.insert(self.uses, id);
+        Some(event.clone())
+    }
+    /// Keeps the event `id` read from its file as last modified at `modified`.
+    pub(super) fn insert(&mut self, id: u64, modified: SystemTime, event: Event) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.remove(id);
+        if self.entries.len() >= self.capacity {
+            if let Some((_, oldest)) = self.by_use.pop_first() {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.uses += 1;
+        self.by_use.insert(self.uses, id);
+        self.entries.insert(id, (event, modified, self.uses));
+    }
+    pub(super) fn remove(&mut self, id: u64) {
+        if let Some((_, _, used)) = self.entries.remove(&id) {
+            self.by_use.remove(&used);
+        }
+    }
+    pub(super) fn clear(&mut self) {
+        self.entries.clear();
+        self.by_use.clear();
+    }
+}
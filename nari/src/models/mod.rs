@@ -1,8 +1,36 @@
+#[cfg(feature = "async")]
+mod async_io;
+mod calendar;
+mod codec;
 mod database;
+mod error;
 pub mod event;
+#[cfg(feature = "ical")]
+mod ical;
 mod id;
+#[cfg(feature = "cache")]
+mod lru;
+#[cfg(feature = "natural")]
+mod natural;
+mod queue;
+mod schema;
+mod store;
+mod timestamp;
 mod user;
 
-pub use self::database::Database;
+#[cfg(feature = "encryption")]
+pub use self::codec::EncryptionKey;
+pub use self::codec::{Codec, SerdeFormat};
+pub use self::database::{
+    CacheMismatch, Database, DatabaseOptions, DatabaseStats, Layout, LockMode, MergeReport,
+    MergeStrategy, VerifyReport,
+};
+pub use self::error::NariError;
 pub use self::id::{EventId, UserId};
+#[cfg(feature = "natural")]
+pub use self::natural::{parse_time, ParseError};
+pub use self::schema::SCHEMA_VERSION;
+pub use self::store::{FileStore, MemoryStore, Store};
+pub use self::timestamp::Timestamp;
 pub use self::user::User;
+pub use ron::ser::PrettyConfig;
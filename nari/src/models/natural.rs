@@ -0,0 +1,180 @@
+use std::fmt;
+
+use super::{
+    calendar::{SECS_PER_DAY, SECS_PER_HOUR},
+    Timestamp,
+};
+
+/// Why [`parse_time`] couldn't read a time, only available with the `natural` feature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    input: String,
+}
+impl ParseError {
+    /// Returns the text that couldn't be read.
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+}
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} isn't a supported time, try something like `in 2 hours` or `tomorrow 9am`",
+            self.input
+        )
+    }
+}
+impl std::error::Error for ParseError {}
+
+/// Reads a time written the way a person would type it, relative to `now`, only
+/// available with the `natural` feature.
+///
+/// Case, extra whitespace and commas are ignored. The supported forms are:
+///
+/// - `now`.
+/// - `in` followed by an amount of time, like `in 2 hours`, `in an hour and 30
+///   minutes` or `in 1h30m`.
+/// - An amount of time followed by `from now`, like `3 days from now`.
+/// - `today` or `tomorrow`, optionally followed by a time of day, like `tomorrow 9am`
+///   or `today at 17:30`. Without one, `tomorrow` is the same time as `now` a day later.
+/// - A time of day alone, optionally after `at`, which is its next occurence: today if
+///   it is still ahead, tomorrow otherwise.
+///
+/// Amounts are whole numbers, or `a` and `an` for one, of seconds (`s`, `sec`, `secs`,
+/// `second`, `seconds`), minutes (`m`, `min`, `mins`, `minute`, `minutes`), hours (`h`,
+/// `hr`, `hrs`, `hour`, `hours`), days (`d`, `day`, `days`) or weeks (`w`, `week`,
+/// `weeks`). Times of day are either on a 24 hour clock like `9`, `09:30` or `21:00`,
+/// on a 12 hour clock like `9am` or `9:30 pm`, or `noon` or `midnight`. They are
+/// always in UTC.
+///
+/// ## Usage
+/// ```
+/// # use nari::models::{parse_time, Timestamp};
+/// // 2023-03-05 10:00:00 UTC
+/// let now = Timestamp(1678010400);
+/// assert_eq!(parse_time("now", now)?, now);
+/// assert_eq!(parse_time("in 2 hours", now)?, now + 2 * 60 * 60);
+/// assert_eq!(parse_time("in an hour and 30 minutes", now)?, now + 90 * 60);
+/// assert_eq!(parse_time("in 1h30m", now)?, now + 90 * 60);
+/// assert_eq!(parse_time("3 days from now", now)?, now + 3 * 24 * 60 * 60);
+/// // 2023-03-06 09:00:00 UTC
+/// assert_eq!(parse_time("Tomorrow 9am", now)?, Timestamp(1678093200));
+/// assert_eq!(parse_time("tomorrow at 09:00", now)?, Timestamp(1678093200));
+/// // Still ahead today
+/// assert_eq!(parse_time("at 17:30", now)?, Timestamp(1678037400));
+/// // Already passed today, so tomorrow
+/// assert_eq!(parse_time("9:00", now)?, Timestamp(1678093200));
+/// assert_eq!(parse_time("noon", now)?, Timestamp(1678017600));
+///
+/// assert!(parse_time("next thursday", now).is_err());
+/// assert!(parse_time("13pm", now).is_err());
+/// # Ok::<(), nari::models::ParseError>(())
+/// ```
+pub fn parse_time(input: &str, now: Timestamp) -> Result<Timestamp, ParseError> {
+    let lower = input.to_lowercase();
+    let tokens = tokenize(&lower);
+    let at = match tokens.as_slice() {
+        ["now"] => Some(now),
+        ["in", amount @ ..] | [amount @ .., "from", "now"] => {
+            parse_duration(amount).and_then(|secs| now.checked_add(secs))
+        }
+        tokens => parse_day_time(tokens, now),
+    };
+    at.ok_or_else(|| ParseError {
+        input: String::from(input),
+    })
+}
+
+// Splits the words, and numbers from the letters next to them, so that `9:30pm` is
+// read like `9:30 pm`
+fn tokenize(input: &str) -> Vec<&str> {
+    let mut tokens = vec![];
+    for word in input.split(|c: char| c.is_whitespace() || c == ',') {
+        let mut start = 0;
+        let mut numeric = None;
+        for (i, c) in word.char_indices() {
+            let is_numeric = c.is_ascii_digit() || c == ':';
+            if numeric.is_some_and(|n| n != is_numeric) {
+                tokens.push(&word[start..i]);
+                start = i;
+            }
+            numeric = Some(is_numeric);
+        }
+        if start < word.len() {
+            tokens.push(&word[start..]);
+        }
+    }
+    tokens
+}
+// Amounts of time like `2 hours and 30 minutes`, in seconds
+fn parse_duration(tokens: &[&str]) -> Option<u64> {
+    let mut total: u64 = 0;
+    let mut tokens = tokens.iter().filter(|t| **t != "and");
+    let mut read_any = false;
+    while let Some(amount) = tokens.next() {
+        let amount = match *amount {
+            "a" | "an" => 1,
+            amount => amount.parse::<u64>().ok()?,
+        };
+        let unit = match *tokens.next()? {
+            "s" | "sec" | "secs" | "second" | "seconds" => 1,
+            "m" | "min" | "mins" | "minute" | "minutes" => 60,
+            "h" | "hr" | "hrs" | "hour" | "hours" => SECS_PER_HOUR,
+            "d" | "day" | "days" => SECS_PER_DAY,
+            "w" | "week" | "weeks" => 7 * SECS_PER_DAY,
+            _ => return None,
+        };
+        total = total.checked_add(amount.checked_mul(unit)?)?;
+        read_any = true;
+    }
+    read_any.then_some(total)
+}
+fn parse_day_time(tokens: &[&str], now: Timestamp) -> Option<Timestamp> {
+    let (days, tokens) = match tokens {
+        ["today", rest @ ..] => (Some(0), rest),
+        ["tomorrow", rest @ ..] => (Some(1), rest),
+        _ => (None, tokens),
+    };
+    let tokens = match tokens {
+        ["at", rest @ ..] => rest,
+        _ => tokens,
+    };
+    if tokens.is_empty() {
+        return now.checked_add(days? * SECS_PER_DAY);
+    }
+    let midnight = now.0 - now.0 % SECS_PER_DAY;
+    let at = midnight.checked_add(parse_time_of_day(tokens)?)?;
+    let at = match days {
+        Some(days) => at.checked_add(days * SECS_PER_DAY)?,
+        None if at > now.0 => at,
+        None => at.checked_add(SECS_PER_DAY)?,
+    };
+    Some(Timestamp(at))
+}
+// Seconds since midnight
+fn parse_time_of_day(tokens: &[&str]) -> Option<u64> {
+    let (clock, half) = match tokens {
+        ["noon"] => return Some(12 * SECS_PER_HOUR),
+        ["midnight"] => return Some(0),
+        [clock] => (*clock, None),
+        [clock, half @ ("am" | "pm")] => (*clock, Some(*half)),
+        _ => return None,
+    };
+    let (hour, minute) = match clock.split_once(':') {
+        Some((hour, minute)) if minute.len() == 2 => (hour, minute.parse::<u64>().ok()?),
+        Some(_) => return None,
+        None => (clock, 0),
+    };
+    let hour = hour.parse::<u64>().ok()?;
+    let hour = match half {
+        None if hour < 24 => hour,
+        Some("am") if (1..=12).contains(&hour) => hour % 12,
+        Some(_) if (1..=12).contains(&hour) => hour % 12 + 12,
+        _ => return None,
+    };
+    if minute >= 60 {
+        return None;
+    }
+    Some(hour * SECS_PER_HOUR + minute * 60)
+}
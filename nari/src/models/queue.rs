@@ -0,0 +1,118 @@
+use std::{collections::BTreeMap, ops::RangeBounds};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// The event cache, holding the ids of the pending events by the unix timestamp of
+/// their next occurence.
+///
+/// Any amount of events can share a timestamp, they are kept in the order they were
+/// queued in.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(super) struct EventQueue(BTreeMap<u64, Vec<u64>>);
+impl EventQueue {
+    pub(super) fn new() -> Self {
+        Self::default()
+    }
+    /// Queues the event `id` at `at`, unless it already is queued there.
+    pub(super) fn insert(&mut self, at: u64, id: u64) {
+        let ids = self.0.entry(at).or_default();
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    /// Takes the event `id` out of `at`, returns whether it was queued there.
+    pub(super) fn remove(&mut self, at: u64, id: u64) -> bool {
+        let Some(ids) = self.0.get_mut(&at) else {
+            return false;
+        };
+        let len = ids.len();
+        ids.retain(|queued| *queued != id);
+        let removed = ids.len() != len;
+        if ids.is_empty() {
+            self.0.remove(&at);
+        }
+        removed
+    }
+    /// Keeps only the events for which `f` returns true.
+    pub(super) fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(u64) -> bool,
+    {
+        self.0.retain(|_, ids| {
+            ids.retain(|id| f(*id));
+            !ids.is_empty()
+        });
+    }
+    /// Takes the event `id` out of the queue wherever it is.
+    pub(super) fn remove_id(&mut self, id: u64) {
+        self.retain(|queued| queued != id);
+    }
+    /// Returns the timestamp and id of the events queued within `range`, in the
+    /// order they fire.
+    pub(super) fn range<R>(&self, range: R) -> impl Iterator<Item = (u64, u64)> + '_
+    where
+        R: RangeBounds<u64>,
+    {
+        self.0
+            .range(range)
+            .flat_map(|(at, ids)| ids.iter().map(move |id| (*at, *id)))
+    }
+    /// Returns the timestamp of the event that fires first.
+    pub(super) fn first_at(&self) -> Option<u64> {
+        self.0.first_key_value().map(|(at, _)| *at)
+    }
+}
+// Saved as the map itself, like the older formats
+impl Serialize for EventQueue {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.0.serialize(serializer)
+    }
+}
+impl<'de> Deserialize<'de> for EventQueue {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // Binary formats were added after the current form, and can't tell the older
+        // ones apart anyway
+        if !deserializer.is_human_readable() {
+            return BTreeMap::deserialize(deserializer).map(Self);
+        }
+        let mut saved = BTreeMap::<u64, SavedIds>::deserialize(deserializer)?;
+        if let Some(SavedIds::Single(0)) = saved.get(&u64::MAX) {
+            saved.remove(&u64::MAX);
+        }
+        let queue = saved
+            .into_iter()
+            .map(|(at, ids)| match ids {
+                SavedIds::Current(ids) => (at, ids),
+                SavedIds::Single(id) => (at, vec![id]),
+            })
+            .collect();
+        Ok(Self(queue))
+    }
+}
+impl Extend<(u64, u64)> for EventQueue {
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = (u64, u64)>,
+    {
+        for (at, id) in iter {
+            self.insert(at, id);
+        }
+    }
+}
+
+/// The forms the events of a timestamp have been saved in.
+///
+/// The older form only had one event per timestamp, along with a placeholder for
+/// event 0 at the last timestamp.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SavedIds {
+    Current(Vec<u64>),
+    Single(u64),
+}
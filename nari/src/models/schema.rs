@@ -0,0 +1,128 @@
+use std::{collections::BTreeMap, path::Path};
+
+use serde::de::DeserializeOwned;
+
+use super::{
+    codec::{Codec, SerdeFormat},
+    database::{read_consolidated, read_path},
+    event::Event,
+    NariError, User,
+};
+
+/// Version of the format users and events are saved in by this version of nari.
+///
+/// Files saved by older versions are upgraded as they are read, so that they keep
+/// working as the format changes. Files saved by newer versions fail to be read.
+///
+/// | Version | Changes                                                          |
+/// |---------|------------------------------------------------------------------|
+/// | 1       | The original format, without a version                           |
+/// | 2       | Adds the version, the new fields of events take their defaults   |
+///
+/// ## Usage
+/// ```
+/// # use nari::models::{Database, EventId, SCHEMA_VERSION};
+/// # fn main() -> std::io::Result<()> {
+/// let dir = std::env::temp_dir().join("nari_schema");
+/// let db = Database::new(&dir)?;
+/// // An event saved before versions existed
+/// let v1 = r#"(
+///     id: (7),
+///     name: "Old event",
+///     description: "",
+///     next_occurence: 1000,
+///     users: [],
+///     repeats: Never,
+///     priority: Low,
+/// )"#;
+/// std::fs::write(dir.join("events").join("7.ron"), v1)?;
+///
+/// let event = db.fetch_event(EventId(7))?.unwrap();
+/// assert_eq!(event.version, SCHEMA_VERSION);
+/// assert_eq!(event.name, "Old event");
+/// assert!(event.tags.is_empty());
+/// # std::fs::remove_dir_all(dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Version of the files saved before versions were introduced.
+pub(super) fn legacy_version() -> u32 {
+    1
+}
+
+/// Something saved with a version of the format.
+pub(super) trait Versioned {
+    fn version_mut(&mut self) -> &mut u32;
+    /// Converts the value from the previous version, `from` being that version.
+    fn upgrade_from(&mut self, _from: u32) {}
+}
+impl Versioned for Event {
+    fn version_mut(&mut self) -> &mut u32 {
+        &mut self.version
+    }
+}
+impl Versioned for User {
+    fn version_mut(&mut self) -> &mut u32 {
+        &mut self.version
+    }
+}
+
+/// Brings `value` up to [`SCHEMA_VERSION`] one version at a time.
+pub(super) fn upgrade<T>(value: &mut T) -> Result<(), NariError>
+where
+    T: Versioned,
+{
+    let version = *value.version_mut();
+    if version > SCHEMA_VERSION {
+        return Err(NariError::InvalidData(format!(
+            "saved with version {version} of the format, only up to {SCHEMA_VERSION} is supported"
+        )));
+    }
+    for from in version..SCHEMA_VERSION {
+        value.upgrade_from(from);
+    }
+    *value.version_mut() = SCHEMA_VERSION;
+    Ok(())
+}
+
+/// Reads every user or event of the consolidated file at `path`, upgrading them to the
+/// current version, none if it wasn't written yet.
+pub(super) fn read_versioned_all<T>(
+    base_path: &Path,
+    path: &Path,
+    format: SerdeFormat,
+    codec: Codec,
+) -> Result<BTreeMap<u64, T>, NariError>
+where
+    T: DeserializeOwned + Versioned,
+{
+    let mut entries: BTreeMap<u64, T> = read_consolidated(base_path, path, format, codec)?;
+    for value in entries.values_mut() {
+        upgrade(value).map_err(|e| e.in_file(path))?;
+    }
+    Ok(entries)
+}
+/// Reads a user or an event from `path`, upgrading it to the current version.
+pub(super) fn read_versioned<T>(
+    base_path: &Path,
+    path: &Path,
+    format: SerdeFormat,
+    codec: Codec,
+) -> Result<T, NariError>
+where
+    T: DeserializeOwned + Versioned,
+{
+    let result = read_path(base_path, path, format, codec).and_then(|mut value| {
+        upgrade(&mut value).map_err(|e| e.in_file(path))?;
+        Ok(value)
+    });
+    #[cfg(feature = "tracing")]
+    if let Err(e) = &result {
+        if !e.is_not_found() {
+            tracing::warn!(path = %path.display(), error = %e, "couldn't read entry");
+        }
+    }
+    result
+}
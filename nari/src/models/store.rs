@@ -0,0 +1,186 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, MutexGuard},
+};
+
+use super::{event::Event, Database, EventId, NariError, Timestamp, User, UserId};
+
+/// The filesystem backed [`Store`], the same as [`Database`].
+pub type FileStore = Database;
+
+/// The operations every backend of nari provides, so that code built on nari can
+/// work with any of them.
+///
+/// [`Database`] keeps everything in the filesystem, while [`MemoryStore`] keeps it
+/// in memory, which is handy for tests.
+///
+/// ## Usage
+/// ```
+/// # use nari::models::{event::EventBuilder, EventId, MemoryStore, Store, User, UserId};
+/// fn invite<S: Store>(store: &S, user: UserId, event: EventId) -> std::io::Result<()> {
+///     if let Some(mut event) = store.fetch_event(event)? {
+///         event.users.insert(user);
+///         store.add_event(event)?;
+///     }
+///     Ok(())
+/// }
+///
+/// # fn main() -> std::io::Result<()> {
+/// let store = MemoryStore::new();
+/// store.add_user(User::new(UserId(1), "Alice"))?;
+/// store.add_event(EventBuilder::new(EventId(1), "Tea time", 1000).build())?;
+///
+/// invite(&store, UserId(1), EventId(1))?;
+/// assert!(store.fetch_user(UserId(1))?.unwrap().events.contains(&EventId(1)));
+/// # Ok(())
+/// # }
+/// ```
+pub trait Store {
+    /// Saves a user, replacing any previous user with the same id.
+    fn add_user(&self, user: User) -> Result<(), NariError>;
+    /// Returns the user with the given id, if any.
+    fn fetch_user(&self, id: UserId) -> Result<Option<User>, NariError>;
+    /// Returns every user, in no particular order.
+    fn list_users(&self) -> Result<Vec<User>, NariError>;
+    /// Removes a user and takes it out of its events, returns whether it existed.
+    fn delete_user(&self, id: UserId) -> Result<bool, NariError>;
+    /// Saves an event and adds it to its users, replacing any previous event with
+    /// the same id.
+    fn add_event(&self, event: Event) -> Result<(), NariError>;
+    /// Replaces a stored event, leaving its users untouched.
+    fn update_event(&self, event: Event) -> Result<(), NariError>;
+    /// Returns the event with the given id, if any.
+    fn fetch_event(&self, id: EventId) -> Result<Option<Event>, NariError>;
+    /// Returns every event, in no particular order.
+    fn list_events(&self) -> Result<Vec<Event>, NariError>;
+    /// Returns the events scheduled from `start` (inclusive) up to `end` (exclusive),
+    /// sorted by their next occurence, leaving out paused events.
+    fn events_between(&self, start: Timestamp, end: Timestamp) -> Result<Vec<Event>, NariError>;
+    /// Removes an event and takes it out of its users, returns whether it existed.
+    fn delete_event(&self, id: EventId) -> Result<bool, NariError>;
+}
+
+impl Store for Database {
+    fn add_user(&self, user: User) -> Result<(), NariError> {
+        Database::add_user(self, user)
+    }
+    fn fetch_user(&self, id: UserId) -> Result<Option<User>, NariError> {
+        Database::fetch_user(self, id)
+    }
+    fn list_users(&self) -> Result<Vec<User>, NariError> {
+        Database::list_users(self)
+    }
+    fn delete_user(&self, id: UserId) -> Result<bool, NariError> {
+        Database::delete_user(self, id)
+    }
+    fn add_event(&self, event: Event) -> Result<(), NariError> {
+        self.insert_event(event)
+    }
+    fn update_event(&self, event: Event) -> Result<(), NariError> {
+        Database::update_event(self, event)
+    }
+    fn fetch_event(&self, id: EventId) -> Result<Option<Event>, NariError> {
+        Database::fetch_event(self, id)
+    }
+    fn list_events(&self) -> Result<Vec<Event>, NariError> {
+        Database::list_events(self)?.collect()
+    }
+    fn events_between(&self, start: Timestamp, end: Timestamp) -> Result<Vec<Event>, NariError> {
+        Database::events_between(self, start, end)
+    }
+    fn delete_event(&self, id: EventId) -> Result<bool, NariError> {
+        Database::delete_event(self, id)
+    }
+}
+
+/// A [`Store`] that keeps everything in memory, nothing is saved once it is dropped.
+///
+/// It can be shared between threads, every operation locks the whole store.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    inner: Mutex<MemoryInner>,
+}
+#[derive(Debug, Default)]
+struct MemoryInner {
+    users: HashMap<UserId, User>,
+    events: HashMap<EventId, Event>,
+}
+impl MemoryStore {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    // A panic while holding the lock can't leave the maps half updated
+    fn lock(&self) -> MutexGuard<'_, MemoryInner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+impl Store for MemoryStore {
+    fn add_user(&self, user: User) -> Result<(), NariError> {
+        self.lock().users.insert(user.id, user);
+        Ok(())
+    }
+    fn fetch_user(&self, id: UserId) -> Result<Option<User>, NariError> {
+        Ok(self.lock().users.get(&id).cloned())
+    }
+    fn list_users(&self) -> Result<Vec<User>, NariError> {
+        Ok(self.lock().users.values().cloned().collect())
+    }
+    fn delete_user(&self, id: UserId) -> Result<bool, NariError> {
+        let mut inner = self.lock();
+        let user = match inner.users.remove(&id) {
+            Some(user) => user,
+            None => return Ok(false),
+        };
+        for e in user.events.iter() {
+            if let Some(event) = inner.events.get_mut(e) {
+                event.users.remove(&id);
+            }
+        }
+        Ok(true)
+    }
+    fn add_event(&self, event: Event) -> Result<(), NariError> {
+        let mut inner = self.lock();
+        for u in event.users.iter() {
+            if let Some(user) = inner.users.get_mut(u) {
+                user.events.insert(event.id);
+            }
+        }
+        inner.events.insert(event.id, event);
+        Ok(())
+    }
+    fn update_event(&self, event: Event) -> Result<(), NariError> {
+        self.lock().events.insert(event.id, event);
+        Ok(())
+    }
+    fn fetch_event(&self, id: EventId) -> Result<Option<Event>, NariError> {
+        Ok(self.lock().events.get(&id).cloned())
+    }
+    fn list_events(&self) -> Result<Vec<Event>, NariError> {
+        Ok(self.lock().events.values().cloned().collect())
+    }
+    fn events_between(&self, start: Timestamp, end: Timestamp) -> Result<Vec<Event>, NariError> {
+        let mut events: Vec<Event> = self
+            .lock()
+            .events
+            .values()
+            .filter(|e| e.enabled && start <= e.next_occurence && e.next_occurence < end)
+            .cloned()
+            .collect();
+        events.sort_by_key(|e| (e.next_occurence, e.id.0));
+        Ok(events)
+    }
+    fn delete_event(&self, id: EventId) -> Result<bool, NariError> {
+        let mut inner = self.lock();
+        let event = match inner.events.remove(&id) {
+            Some(event) => event,
+            None => return Ok(false),
+        };
+        for u in event.users.iter() {
+            if let Some(user) = inner.users.get_mut(u) {
+                user.events.remove(&id);
+            }
+        }
+        Ok(true)
+    }
+}
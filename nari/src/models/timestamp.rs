@@ -0,0 +1,154 @@
+use std::{
+    fmt::Display,
+    ops::{Add, Sub},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// A unix timestamp in seconds, it converts from and into a plain `u64`.
+///
+/// It is saved as the bare number, so files don't tell it apart from a `u64`.
+///
+/// ## Usage
+/// ```
+/// # use std::time::{Duration, UNIX_EPOCH};
+/// # use nari::models::Timestamp;
+/// let at: Timestamp = 1000.into();
+/// assert_eq!(at + 60, Timestamp(1060));
+/// assert_eq!(at.saturating_sub(2000), Timestamp(0));
+/// assert_eq!(at.to_system_time(), Some(UNIX_EPOCH + Duration::from_secs(1000)));
+/// let raw: u64 = at.into();
+/// assert_eq!(raw, 1000);
+/// ```
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct Timestamp(pub u64);
+impl Timestamp {
+    /// Returns the current time, or the unix epoch if the clock is set before it.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use std::time::{SystemTime, UNIX_EPOCH};
+    /// # use nari::models::Timestamp;
+    /// let before = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    /// let now = Timestamp::now();
+    /// let after = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    /// assert!(before <= now.0 && now.0 <= after);
+    /// ```
+    pub fn now() -> Self {
+        Self::from_system_time(SystemTime::now())
+    }
+    /// Returns the whole seconds from the unix epoch to `time`, or the unix epoch if
+    /// `time` is before it.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use std::time::{Duration, UNIX_EPOCH};
+    /// # use nari::models::Timestamp;
+    /// let time = UNIX_EPOCH + Duration::from_millis(1500);
+    /// assert_eq!(Timestamp::from_system_time(time), Timestamp(1));
+    /// let before = UNIX_EPOCH - Duration::from_secs(1);
+    /// assert_eq!(Timestamp::from_system_time(before), Timestamp(0));
+    /// ```
+    pub fn from_system_time(time: SystemTime) -> Self {
+        Self(time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()))
+    }
+    /// Returns the timestamp as a [`SystemTime`], or `None` if it is too far in the
+    /// future for the platform to represent.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use std::time::{Duration, UNIX_EPOCH};
+    /// # use nari::models::Timestamp;
+    /// let at = Timestamp(90);
+    /// assert_eq!(at.to_system_time(), Some(UNIX_EPOCH + Duration::from_secs(90)));
+    /// assert_eq!(Timestamp(u64::MAX).to_system_time(), None);
+    /// ```
+    pub fn to_system_time(self) -> Option<SystemTime> {
+        UNIX_EPOCH.checked_add(Duration::from_secs(self.0))
+    }
+    /// Returns the seconds since the unix epoch, same as `.0`.
+    pub fn as_secs(self) -> u64 {
+        self.0
+    }
+    /// Returns the timestamp `secs` seconds later, or `None` if it overflows.
+    pub fn checked_add(self, secs: u64) -> Option<Self> {
+        self.0.checked_add(secs).map(Self)
+    }
+    /// Returns the timestamp `secs` seconds earlier, or `None` if it would be before
+    /// the unix epoch.
+    pub fn checked_sub(self, secs: u64) -> Option<Self> {
+        self.0.checked_sub(secs).map(Self)
+    }
+    /// Returns the timestamp `secs` seconds later, stopping at `Timestamp(u64::MAX)`.
+    pub fn saturating_add(self, secs: u64) -> Self {
+        Self(self.0.saturating_add(secs))
+    }
+    /// Returns the timestamp `secs` seconds earlier, stopping at the unix epoch.
+    pub fn saturating_sub(self, secs: u64) -> Self {
+        Self(self.0.saturating_sub(secs))
+    }
+}
+
+impl Display for Timestamp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+impl From<u64> for Timestamp {
+    fn from(secs: u64) -> Self {
+        Self(secs)
+    }
+}
+impl From<Timestamp> for u64 {
+    fn from(at: Timestamp) -> Self {
+        at.0
+    }
+}
+impl From<SystemTime> for Timestamp {
+    fn from(time: SystemTime) -> Self {
+        Self::from_system_time(time)
+    }
+}
+// Plain seconds can be compared against directly, to ease moving from bare `u64`
+impl PartialEq<u64> for Timestamp {
+    fn eq(&self, other: &u64) -> bool {
+        self.0 == *other
+    }
+}
+impl PartialOrd<u64> for Timestamp {
+    fn partial_cmp(&self, other: &u64) -> Option<std::cmp::Ordering> {
+        self.0.partial_cmp(other)
+    }
+}
+impl Add<u64> for Timestamp {
+    type Output = Self;
+
+    fn add(self, secs: u64) -> Self {
+        Self(self.0 + secs)
+    }
+}
+impl Sub<u64> for Timestamp {
+    type Output = Self;
+
+    fn sub(self, secs: u64) -> Self {
+        Self(self.0 - secs)
+    }
+}
+impl Add<Duration> for Timestamp {
+    type Output = Self;
+
+    fn add(self, duration: Duration) -> Self {
+        self + duration.as_secs()
+    }
+}
+impl Sub<Duration> for Timestamp {
+    type Output = Self;
+
+    fn sub(self, duration: Duration) -> Self {
+        self - duration.as_secs()
+    }
+}
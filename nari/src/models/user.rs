@@ -1,14 +1,40 @@
-use std::collections::HashSet;
+use std::{
+    borrow::Borrow,
+    collections::HashSet,
+    hash::{Hash, Hasher},
+};
 
 use serde::{Deserialize, Serialize};
 
-use super::{EventId, UserId};
+use super::{
+    schema::{legacy_version, SCHEMA_VERSION},
+    EventId, UserId,
+};
 
+/// A user of the database, taking part in any number of events.
+///
+/// A user is identified only by its [`id`](User::id): two users with the same id are
+/// equal and hash the same even if their names or events differ. This lets a
+/// `HashSet<User>` hold one user per id and be looked up by [`UserId`].
+///
+/// ## Usage
+/// ```
+/// # use std::collections::HashSet;
+/// # use nari::models::{User, UserId};
+/// let mut users = HashSet::new();
+/// users.insert(User::new(UserId(1), "Alice"));
+/// assert!(!users.insert(User::new(UserId(1), "Alice again")));
+///
+/// assert_eq!(users.get(&UserId(1)).unwrap().name, "Alice");
+/// ```
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct User {
     pub id: UserId,
     pub name: String,
     pub events: HashSet<EventId>,
+    /// Version of the format the user is saved in, see [`SCHEMA_VERSION`].
+    #[serde(default = "legacy_version")]
+    pub version: u32,
 }
 impl User {
     pub fn new(id: UserId, name: &str) -> Self {
@@ -16,8 +42,28 @@ impl User {
             id,
             name: String::from(name),
             events: HashSet::new(),
+            version: SCHEMA_VERSION,
         }
     }
+    /// Removes an event from the user, returns whether the user was in it.
+    ///
+    /// Only the user is changed, see [`Database::remove_event_from_user`] to also
+    /// remove the user from the stored event.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use nari::models::{EventId, User, UserId};
+    /// let mut alice = User::new(UserId(42), "Alice");
+    /// alice.events.insert(EventId(1));
+    ///
+    /// assert!(alice.remove_event(EventId(1)));
+    /// assert!(!alice.remove_event(EventId(1)));
+    /// ```
+    ///
+    /// [`Database::remove_event_from_user`]: super::Database::remove_event_from_user
+    pub fn remove_event(&mut self, id: EventId) -> bool {
+        self.events.remove(&id)
+    }
 }
 impl PartialEq for User {
     fn eq(&self, other: &Self) -> bool {
@@ -25,3 +71,14 @@ impl PartialEq for User {
     }
 }
 impl Eq for User {}
+// Hashes only the id, to agree with `PartialEq`
+impl Hash for User {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+impl Borrow<UserId> for User {
+    fn borrow(&self) -> &UserId {
+        &self.id
+    }
+}